@@ -1,15 +1,20 @@
 use std::thread::sleep;
 
 use super::config::*;
+use super::gui_pages::show_toasts;
+use crate::bindings::*;
 use crate::game::*;
 use crate::input::*;
 use crate::instance::*;
-use crate::launch::launch_game;
+use crate::launch::{add_to_session, launch_game};
+use crate::prefix::PrefixInfo;
+use crate::proton::*;
+use crate::session::*;
 use crate::util::*;
 
 use eframe::egui::{self, Key};
 
-#[derive(Eq, PartialEq)]
+#[derive(Eq, PartialEq, Clone, Copy)]
 pub enum MenuPage {
     Home,
     Settings,
@@ -22,6 +27,9 @@ pub enum MenuPage {
 pub enum SettingsPage {
     General,
     Gamescope,
+    Prefixes,
+    Remapping,
+    Controls,
 }
 
 pub struct PartyApp {
@@ -34,9 +42,18 @@ pub struct PartyApp {
     pub input_devices: Vec<InputDevice>,
     pub instances: Vec<Instance>,
     pub instance_add_dev: Option<usize>,
+    // Device connect/disconnect messages and when they were shown
+    pub toasts: Vec<(String, std::time::Instant)>,
     pub games: Vec<Game>,
     pub selected_game: usize,
     pub profiles: Vec<String>,
+    pub proton_builds: Vec<ProtonBuild>,
+    pub prefixes: Vec<PrefixInfo>,
+    // Runtime label and installed runtimes for the game page, looked up once per game
+    pub runtime_info: Option<(String, String, String)>,
+    pub selected_remap: usize,
+    // Action waiting for a button press to bind, in the controls settings
+    pub binding_capture: Option<UiAction>,
 
     pub loading_msg: Option<String>,
     pub loading_since: Option<std::time::Instant>,
@@ -53,6 +70,7 @@ macro_rules! cur_game {
 impl Default for PartyApp {
     fn default() -> Self {
         let options = load_cfg();
+        watch_input_devices();
         let input_devices = scan_input_devices(&options.pad_filter_type);
         Self {
             needs_update: check_for_partydeck_update(),
@@ -63,9 +81,15 @@ impl Default for PartyApp {
             input_devices,
             instances: Vec::new(),
             instance_add_dev: None,
+            toasts: Vec::new(),
             games: scan_all_games(),
             selected_game: 0,
             profiles: Vec::new(),
+            proton_builds: scan_proton_builds(),
+            prefixes: Vec::new(),
+            runtime_info: None,
+            selected_remap: 0,
+            binding_capture: None,
             loading_msg: None,
             loading_since: None,
             task: None,
@@ -75,9 +99,24 @@ impl Default for PartyApp {
 
 impl eframe::App for PartyApp {
     fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
-        if !raw_input.focused || self.task.is_some() {
+        // Presses made while another window had focus, e.g. during a game, are thrown away
+        let refocused = raw_input
+            .events
+            .iter()
+            .any(|event| matches!(event, egui::Event::WindowFocused(true)));
+        if !raw_input.focused || self.task.is_some() || refocused {
+            for dev in &mut self.input_devices {
+                dev.discard_events();
+            }
             return;
         }
+        if let Some(action) = self.binding_capture {
+            if self.cur_page == MenuPage::Settings && self.settings_page == SettingsPage::Controls {
+                self.handle_binding_capture(action);
+                return;
+            }
+            self.binding_capture = None;
+        }
         match self.cur_page {
             MenuPage::Instances => self.handle_devices_instance_menu(),
             _ => self.handle_gamepad_gui(raw_input),
@@ -85,6 +124,21 @@ impl eframe::App for PartyApp {
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if input_devices_changed() {
+            let instance_count = self.instances.len();
+            let messages = rescan_input_devices(
+                &mut self.input_devices,
+                &mut self.instances,
+                &self.options.pad_filter_type,
+            );
+            if self.instances.len() != instance_count {
+                self.instance_add_dev = None;
+            }
+            let now = std::time::Instant::now();
+            self.toasts
+                .extend(messages.into_iter().map(|msg| (msg, now)));
+        }
+
         egui::TopBottomPanel::top("menu_nav_panel").show(ctx, |ui| {
             if self.task.is_some() {
                 ui.disable();
@@ -140,6 +194,11 @@ impl eframe::App for PartyApp {
                 self.task = Some(handle);
             }
         }
+        // Copies report their own progress, so they don't count towards the timeout
+        let copying = COPY_PROGRESS.active();
+        if copying && self.loading_since.is_some() {
+            self.loading_since = Some(std::time::Instant::now());
+        }
         if let Some(start) = self.loading_since {
             if start.elapsed() > std::time::Duration::from_secs(60) {
                 // Give up waiting after one minute
@@ -147,9 +206,10 @@ impl eframe::App for PartyApp {
             }
         }
         if let Some(msg) = &self.loading_msg {
+            let session = session_running();
             egui::Area::new("loading".into())
                 .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-                .interactable(false)
+                .interactable(session || copying)
                 .show(ctx, |ui| {
                     egui::Frame::NONE
                         .fill(egui::Color32::from_rgba_premultiplied(0, 0, 0, 192))
@@ -160,10 +220,29 @@ impl eframe::App for PartyApp {
                                 ui.add(egui::widgets::Spinner::new().size(40.0));
                                 ui.add_space(8.0);
                                 ui.label(msg);
+                                if copying {
+                                    let (fraction, text) = COPY_PROGRESS.status();
+                                    ui.add_space(8.0);
+                                    ui.add(
+                                        egui::ProgressBar::new(fraction)
+                                            .desired_width(300.0)
+                                            .text(text),
+                                    );
+                                    if ui.button("Cancel").clicked() {
+                                        COPY_PROGRESS.cancel();
+                                    }
+                                }
+                                if session {
+                                    ui.add_space(8.0);
+                                    if ui.button("⏹ End Session").clicked() {
+                                        request_end_session();
+                                    }
+                                }
                             });
                         });
                 });
         }
+        show_toasts(ctx, &mut self.toasts);
         if ctx.input(|input| input.focused) {
             ctx.request_repaint_after(std::time::Duration::from_millis(33)); // 30 fps
         }
@@ -181,38 +260,30 @@ impl PartyApp {
     }
 
     fn handle_gamepad_gui(&mut self, raw_input: &mut egui::RawInput) {
-        let mut key: Option<egui::Key> = None;
+        let mut keys: Vec<egui::Key> = Vec::new();
+        let mut presses: Vec<PadButton> = Vec::new();
         for pad in &mut self.input_devices {
             if !pad.enabled() {
                 continue;
             }
-            match pad.poll() {
-                Some(PadButton::ABtn) => key = Some(Key::Enter),
-                Some(PadButton::BBtn) => self.cur_page = MenuPage::Home,
-                Some(PadButton::XBtn) => {
-                    self.profiles = scan_profiles(false);
-                    self.cur_page = MenuPage::Profiles;
-                }
-                Some(PadButton::YBtn) => self.cur_page = MenuPage::Settings,
-                Some(PadButton::SelectBtn) => key = Some(Key::Tab),
-                Some(PadButton::StartBtn) => {
-                    if self.cur_page == MenuPage::Game {
-                        self.instances.clear();
-                        self.profiles = scan_profiles(true);
-                        self.instance_add_dev = None;
-                        self.cur_page = MenuPage::Instances;
-                    }
-                }
-                Some(PadButton::Up) => key = Some(Key::ArrowUp),
-                Some(PadButton::Down) => key = Some(Key::ArrowDown),
-                Some(PadButton::Left) => key = Some(Key::ArrowLeft),
-                Some(PadButton::Right) => key = Some(Key::ArrowRight),
-                Some(_) => {}
-                None => {}
+            while let Some(btn) = pad.poll() {
+                presses.push(btn);
             }
         }
+        for btn in presses {
+            let key = match btn {
+                PadButton::Up => Some(Key::ArrowUp),
+                PadButton::Down => Some(Key::ArrowDown),
+                PadButton::Left => Some(Key::ArrowLeft),
+                PadButton::Right => Some(Key::ArrowRight),
+                PadButton::Press(code) => {
+                    self.handle_menu_action(self.options.bindings.action(code, &MENU_ACTIONS))
+                }
+            };
+            keys.extend(key);
+        }
 
-        if let Some(key) = key {
+        for key in keys {
             raw_input.events.push(egui::Event::Key {
                 key,
                 physical_key: None,
@@ -223,6 +294,62 @@ impl PartyApp {
         }
     }
 
+    // Carries out a menu action, returning the key it stands in for if it's one
+    fn handle_menu_action(&mut self, action: Option<UiAction>) -> Option<Key> {
+        match action? {
+            UiAction::Confirm => return Some(Key::Enter),
+            UiAction::NextWidget => return Some(Key::Tab),
+            UiAction::Home => self.cur_page = MenuPage::Home,
+            UiAction::Profiles => {
+                self.profiles = scan_profiles(false);
+                self.cur_page = MenuPage::Profiles;
+            }
+            UiAction::Settings => self.cur_page = MenuPage::Settings,
+            UiAction::Play => {
+                if self.cur_page == MenuPage::Game {
+                    self.instances.clear();
+                    self.profiles = scan_profiles(true);
+                    self.instance_add_dev = None;
+                    self.cur_page = MenuPage::Instances;
+                }
+            }
+            UiAction::PrevTab => self.switch_tab(-1),
+            UiAction::NextTab => self.switch_tab(1),
+            _ => {}
+        }
+        None
+    }
+
+    // Binds the next button or key pressed on any device to the action being bound
+    fn handle_binding_capture(&mut self, action: UiAction) {
+        for pad in &mut self.input_devices {
+            while let Some(btn) = pad.poll() {
+                // Left click is left alone, as it's how the settings are used
+                if let PadButton::Press(code) = btn
+                    && code != evdev::KeyCode::BTN_LEFT
+                {
+                    self.options.bindings.bind(action, code);
+                    self.binding_capture = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    // Moves between the tabs of the top panel, from Home if the current page isn't one
+    fn switch_tab(&mut self, step: isize) {
+        const TABS: [MenuPage; 3] = [MenuPage::Home, MenuPage::Settings, MenuPage::Profiles];
+        let cur = TABS
+            .iter()
+            .position(|page| *page == self.cur_page)
+            .unwrap_or(0) as isize;
+        let next = (cur + step).rem_euclid(TABS.len() as isize) as usize;
+        if TABS[next] == MenuPage::Profiles {
+            self.profiles = scan_profiles(false);
+        }
+        self.cur_page = TABS[next];
+    }
+
     fn handle_devices_instance_menu(&mut self) {
         let mut i = 0;
         while i < self.input_devices.len() {
@@ -230,9 +357,15 @@ impl PartyApp {
                 i += 1;
                 continue;
             }
-            match self.input_devices[i].poll() {
-                Some(PadButton::ABtn) | Some(PadButton::ZKey) | Some(PadButton::RightClick) => {
-                    if self.input_devices[i].device_type() != DeviceType::Gamepad
+            let action = match self.input_devices[i].poll() {
+                Some(PadButton::Press(code)) => {
+                    self.options.bindings.action(code, &INSTANCE_ACTIONS)
+                }
+                _ => None,
+            };
+            match action {
+                Some(UiAction::AddDevice) => {
+                    if !self.input_devices[i].device_type().is_controller()
                         && !self.options.kbm_support
                     {
                         continue;
@@ -243,10 +376,14 @@ impl PartyApp {
                     }
                     // Prevent same keyboard/mouse device in multiple instances due to current custom gamescope limitations
                     // TODO: Remove this when custom gamescope supports the same keyboard/mouse device for multiple instances
-                    if self.input_devices[i].device_type() != DeviceType::Gamepad
+                    if !self.input_devices[i].device_type().is_controller()
                         && self.is_device_in_any_instance(i) {
                         continue;
                     }
+                    // Devices already playing in the running session can't join again
+                    if self.is_device_in_session(i) {
+                        continue;
+                    }
 
                     match self.instance_add_dev {
                         Some(inst) => {
@@ -258,17 +395,23 @@ impl PartyApp {
                             else { continue; }
                         }
                         None => {
+                            // Start with the profile that last played with this device
+                            let profselection = remembered_profile(
+                                &self.input_devices[i].id(),
+                                &self.profiles,
+                                &self.instances,
+                            );
                             self.instances.push(Instance {
                                 devices: vec![i],
                                 profname: String::new(),
-                                profselection: 0,
+                                profselection,
                                 width: 0,
                                 height: 0,
                             });
                         }
                     }
                 }
-                Some(PadButton::BBtn) | Some(PadButton::XKey) => {
+                Some(UiAction::RemoveDevice) => {
                     if self.instance_add_dev != None {
                         self.instance_add_dev = None;
                     } else if self.is_device_in_any_instance(i) {
@@ -277,16 +420,20 @@ impl PartyApp {
                         self.cur_page = MenuPage::Game;
                     }
                 }
-                Some(PadButton::YBtn) | Some(PadButton::AKey) => {
+                Some(UiAction::Invite) => {
                     if self.instance_add_dev == None {
                         if let Some((instance, _)) = self.find_device_in_instance(i) {
                             self.instance_add_dev = Some(instance);
                         }
                     }
                 }
-                Some(PadButton::StartBtn) => {
+                Some(UiAction::Launch) => {
                     if self.instances.len() > 0 && self.is_device_in_any_instance(i) {
-                        self.prepare_game_launch();
+                        if session_running() {
+                            self.prepare_session_join();
+                        } else {
+                            self.prepare_game_launch();
+                        }
                     }
                 }
                 _ => {}
@@ -304,6 +451,16 @@ impl PartyApp {
         false
     }
 
+    fn is_device_in_session(&self, dev: usize) -> bool {
+        match session_info() {
+            Some(info) => {
+                let dev = &self.input_devices[dev];
+                find_device(&info.devices, &dev.id(), dev.path()).is_some()
+            }
+            None => false,
+        }
+    }
+
     fn is_device_in_instance(&self, instance_index: usize, dev: usize) -> bool {
         if self.instances[instance_index].devices.contains(&dev) {
             return true;
@@ -356,6 +513,48 @@ impl PartyApp {
         }
     }
 
+    pub fn end_session(&mut self) {
+        self.spawn_task("Ending session...", || {
+            request_end_session();
+            while session_running() {
+                sleep(std::time::Duration::from_millis(100));
+            }
+        });
+    }
+
+    // Stores changed per-game settings, saving them right away
+    pub fn set_game_settings(&mut self, uid: &str, settings: GameSettings) {
+        if self.options.game_settings(uid) == settings {
+            return;
+        }
+        self.options.game_settings.insert(uid.to_string(), settings);
+        if let Err(e) = save_cfg(&self.options) {
+            msg("Error", &format!("Couldn't save settings: {}", e));
+        }
+    }
+
+    // Launches the pending instances into the running session
+    pub fn prepare_session_join(&mut self) {
+        set_instance_names(&mut self.instances, &self.profiles);
+
+        let instances = std::mem::take(&mut self.instances);
+        let dev_infos: Vec<DeviceInfo> = self.input_devices.iter().map(|p| p.info()).collect();
+
+        self.cur_page = MenuPage::Home;
+        self.spawn_task(
+            "Adding players...\n\nDon't press any buttons or move any analog sticks or mice.",
+            move || {
+                for instance in &instances {
+                    if let Err(err) = add_to_session(&dev_infos, instance) {
+                        println!("{}", err);
+                        msg("Launch Error", &format!("{err}"));
+                        break;
+                    }
+                }
+            },
+        );
+    }
+
     pub fn prepare_game_launch(&mut self) {
         set_instance_resolutions(&mut self.instances, &self.options);
         set_instance_names(&mut self.instances, &self.profiles);
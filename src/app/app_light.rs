@@ -1,10 +1,15 @@
 use std::thread::sleep;
 
 use super::config::*;
+use super::gui_pages::{binding_hint, proton_version_combo, show_toasts};
+use crate::bindings::*;
 use crate::game::*;
 use crate::input::*;
 use crate::instance::*;
 use crate::launch::launch_game;
+use crate::proton::*;
+use crate::runner::RunnerType;
+use crate::session::*;
 use crate::util::*;
 
 use std::path::PathBuf;
@@ -26,7 +31,10 @@ pub struct LightPartyApp {
     pub input_devices: Vec<InputDevice>,
     pub instances: Vec<Instance>,
     pub instance_add_dev: Option<usize>,
+    // Device connect/disconnect messages and when they were shown
+    pub toasts: Vec<(String, std::time::Instant)>,
     pub game: Game,
+    pub proton_builds: Vec<ProtonBuild>,
 
     pub loading_msg: Option<String>,
     pub loading_since: Option<std::time::Instant>,
@@ -37,6 +45,7 @@ pub struct LightPartyApp {
 impl LightPartyApp {
     pub fn new_lightapp(exec: String, execargs: String) -> Self {
         let options = load_cfg();
+        watch_input_devices();
         let input_devices = scan_input_devices(&options.pad_filter_type);
         // placeholder, user should define this
         Self {
@@ -46,8 +55,10 @@ impl LightPartyApp {
             input_devices,
             instances: Vec::new(),
             instance_add_dev: None,
+            toasts: Vec::new(),
             // Placeholder, user should define this with program args
             game: Game::ExecRef(Executable::new(PathBuf::from(exec), execargs)),
+            proton_builds: scan_proton_builds(),
             loading_msg: None,
             loading_since: None,
             task: None,
@@ -57,7 +68,15 @@ impl LightPartyApp {
 
 impl eframe::App for LightPartyApp {
     fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
-        if !raw_input.focused || self.task.is_some() {
+        // Presses made while another window had focus, e.g. during a game, are thrown away
+        let refocused = raw_input
+            .events
+            .iter()
+            .any(|event| matches!(event, egui::Event::WindowFocused(true)));
+        if !raw_input.focused || self.task.is_some() || refocused {
+            for dev in &mut self.input_devices {
+                dev.discard_events();
+            }
             return;
         }
         if self.cur_page == MenuPage::Instances {
@@ -66,6 +85,21 @@ impl eframe::App for LightPartyApp {
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if input_devices_changed() {
+            let instance_count = self.instances.len();
+            let messages = rescan_input_devices(
+                &mut self.input_devices,
+                &mut self.instances,
+                &self.options.pad_filter_type,
+            );
+            if self.instances.len() != instance_count {
+                self.instance_add_dev = None;
+            }
+            let now = std::time::Instant::now();
+            self.toasts
+                .extend(messages.into_iter().map(|msg| (msg, now)));
+        }
+
         egui::TopBottomPanel::top("menu_nav_panel").show(ctx, |ui| {
             if self.task.is_some() {
                 ui.disable();
@@ -108,6 +142,11 @@ impl eframe::App for LightPartyApp {
                 self.task = Some(handle);
             }
         }
+        // Copies report their own progress, so they don't count towards the timeout
+        let copying = COPY_PROGRESS.active();
+        if copying && self.loading_since.is_some() {
+            self.loading_since = Some(std::time::Instant::now());
+        }
         if let Some(start) = self.loading_since {
             if start.elapsed() > std::time::Duration::from_secs(60) {
                 // Give up waiting after one minute
@@ -115,9 +154,10 @@ impl eframe::App for LightPartyApp {
             }
         }
         if let Some(msg) = &self.loading_msg {
+            let session = session_running();
             egui::Area::new("loading".into())
                 .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
-                .interactable(false)
+                .interactable(session || copying)
                 .show(ctx, |ui| {
                     egui::Frame::NONE
                         .fill(egui::Color32::from_rgba_premultiplied(0, 0, 0, 192))
@@ -128,10 +168,29 @@ impl eframe::App for LightPartyApp {
                                 ui.add(egui::widgets::Spinner::new().size(40.0));
                                 ui.add_space(8.0);
                                 ui.label(msg);
+                                if copying {
+                                    let (fraction, text) = COPY_PROGRESS.status();
+                                    ui.add_space(8.0);
+                                    ui.add(
+                                        egui::ProgressBar::new(fraction)
+                                            .desired_width(300.0)
+                                            .text(text),
+                                    );
+                                    if ui.button("Cancel").clicked() {
+                                        COPY_PROGRESS.cancel();
+                                    }
+                                }
+                                if session {
+                                    ui.add_space(8.0);
+                                    if ui.button("⏹ End Session").clicked() {
+                                        request_end_session();
+                                    }
+                                }
                             });
                         });
                 });
         }
+        show_toasts(ctx, &mut self.toasts);
         if ctx.input(|input| input.focused) {
             ctx.request_repaint_after(std::time::Duration::from_millis(33)); // 30 fps
         }
@@ -155,9 +214,15 @@ impl LightPartyApp {
                 i += 1;
                 continue;
             }
-            match self.input_devices[i].poll() {
-                Some(PadButton::ABtn) | Some(PadButton::ZKey) | Some(PadButton::RightClick) => {
-                    if self.input_devices[i].device_type() != DeviceType::Gamepad
+            let action = match self.input_devices[i].poll() {
+                Some(PadButton::Press(code)) => {
+                    self.options.bindings.action(code, &INSTANCE_ACTIONS)
+                }
+                _ => None,
+            };
+            match action {
+                Some(UiAction::AddDevice) => {
+                    if !self.input_devices[i].device_type().is_controller()
                         && !self.options.kbm_support
                     {
                         continue;
@@ -168,7 +233,7 @@ impl LightPartyApp {
                     }
                     // Prevent same keyboard/mouse device in multiple instances due to current custom gamescope limitations
                     // TODO: Remove this when custom gamescope supports the same keyboard/mouse device for multiple instances
-                    if self.input_devices[i].device_type() != DeviceType::Gamepad
+                    if !self.input_devices[i].device_type().is_controller()
                         && self.is_device_in_any_instance(i) {
                         continue;
                     }
@@ -193,21 +258,21 @@ impl LightPartyApp {
                         }
                     }
                 }
-                Some(PadButton::BBtn) | Some(PadButton::XKey) => {
+                Some(UiAction::RemoveDevice) => {
                     if self.instance_add_dev != None {
                         self.instance_add_dev = None;
                     } else if self.is_device_in_any_instance(i) {
                         self.remove_device(i);
                     }
                 }
-                Some(PadButton::YBtn) | Some(PadButton::AKey) => {
+                Some(UiAction::Invite) => {
                     if self.instance_add_dev == None {
                         if let Some((instance, _)) = self.find_device_in_instance(i) {
                             self.instance_add_dev = Some(instance);
                         }
                     }
                 }
-                Some(PadButton::StartBtn) => {
+                Some(UiAction::Launch) => {
                     if self.instances.len() > 0 && self.is_device_in_any_instance(i) {
                         self.prepare_game_launch();
                     }
@@ -297,6 +362,9 @@ impl LightPartyApp {
                     println!("{}", err);
                     msg("Launch Error", &format!("{err}"));
                 }
+                while session_running() {
+                    sleep(std::time::Duration::from_millis(250));
+                }
                 std::process::exit(0);
             },
         );
@@ -440,16 +508,62 @@ impl LightPartyApp {
         });
 
         ui.horizontal(|ui| {
-        let proton_ver_label = ui.label("Proton version");
-        let proton_ver_editbox = ui.add(
-            egui::TextEdit::singleline(&mut self.options.proton_version)
-                .hint_text("GE-Proton"),
-        );
-        if proton_ver_label.hovered() || proton_ver_editbox.hovered() {
-            self.infotext = "Specify a Proton version. This can be a path, e.g. \"/path/to/proton\" or just a name, e.g. \"GE-Proton\" for the latest version of Proton-GE. If left blank, this will default to \"GE-Proton\". If unsure, leave this blank.".to_string();
-        }
+            let proton_ver_label = ui.label("Proton version");
+            let proton_ver_combo = proton_version_combo(
+                ui,
+                "proton_version",
+                &mut self.options.proton_version,
+                &self.proton_builds,
+                "Default (latest GE-Proton)",
+            );
+            if ui.button("🔄").clicked() {
+                self.proton_builds = scan_proton_builds();
+            }
+            if proton_ver_label.hovered() || proton_ver_combo.hovered() {
+                self.infotext = "Select the Proton build used to run Windows games. Builds are found in Steam's compatibilitytools.d folder, your Steam libraries and umu's cache. \"GE-Proton\" and \"UMU-Proton\" are downloaded automatically by umu if they aren't installed. If unsure, leave this on the default.".to_string();
+            }
         });
 
+        ui.horizontal(|ui| {
+            let runner_label = ui.label("Windows runner");
+            let r1 = ui.radio_value(
+                &mut self.options.runner,
+                RunnerType::Umu,
+                RunnerType::Umu.display(),
+            );
+            let r2 = ui.radio_value(
+                &mut self.options.runner,
+                RunnerType::Wine,
+                RunnerType::Wine.display(),
+            );
+            if runner_label.hovered() || r1.hovered() || r2.hovered() {
+                self.infotext = "Select how Windows games are run. Proton (umu) runs games through umu-launcher with the selected Proton build. Wine runs games with a system Wine, Wine-GE or Lutris runner of your choice. Individual games can override this on their page. If unsure, leave this on Proton.".to_string();
+            }
+        });
+
+        if self.options.uses_wine() {
+            ui.horizontal(|ui| {
+                let wine_label = ui.label("Wine binary");
+                let wine_editbox = ui.add(
+                    egui::TextEdit::singleline(&mut self.options.wine_path).hint_text("wine"),
+                );
+                if wine_label.hovered() || wine_editbox.hovered() {
+                    self.infotext = "Path to the wine binary used for games run with Wine, whether by default or on their own page, e.g. \"~/.local/share/lutris/runners/wine/wine-ge-8-26-x86_64/bin/wine\". If left blank, the wine found in your PATH is used.".to_string();
+                }
+            });
+            ui.horizontal(|ui| {
+                let dxvk_check = ui.checkbox(&mut self.options.wine_dxvk, "Install DXVK");
+                let dxvk_editbox = ui.add_enabled(
+                    self.options.wine_dxvk,
+                    egui::TextEdit::singleline(&mut self.options.dxvk_path)
+                        .hint_text("/path/to/dxvk"),
+                );
+                if dxvk_check.hovered() || dxvk_editbox.hovered() {
+                    self.infotext = "Installs DXVK into each Wine prefix before launching. Point this to an extracted DXVK release folder containing the x64 and x32 folders.".to_string();
+                }
+            });
+        }
+
         let proton_separate_pfxs_check = ui.checkbox(
             &mut self.options.proton_separate_pfxs,
             "Run instances in separate Proton prefixes",
@@ -458,6 +572,17 @@ impl LightPartyApp {
             self.infotext = "Runs each instance in its own Proton prefix. If unsure, leave this unchecked. This option will take up more space on the disk, but may also help with certain Proton-related issues such as only one instance of a game starting.".to_string();
         }
 
+        let proton_profile_pfxs_check = ui.add_enabled(
+            self.options.proton_separate_pfxs,
+            egui::Checkbox::new(
+                &mut self.options.proton_profile_pfxs,
+                "Give each profile its own prefix",
+            ),
+        );
+        if proton_profile_pfxs_check.hovered() {
+            self.infotext = "Keeps a separate prefix for each profile instead of each instance slot, so registry settings and other data games keep in the prefix follow the player. Guests get a fresh prefix that is erased when the session ends.".to_string();
+        }
+
         let allow_multiple_instances_on_same_device_check = ui.checkbox(
             &mut self.options.allow_multiple_instances_on_same_device,
             "Allow multiple instances on the same device",
@@ -466,6 +591,20 @@ impl LightPartyApp {
             self.infotext = "Allow multiple instances on the same device. This can be useful for testing or when one person wants to control multiple instances.".to_string();
         }
 
+        let isolated_network_check = ui.checkbox(
+            &mut self.options.isolated_network,
+            "Isolate instances from the network",
+        );
+        if isolated_network_check.hovered() {
+            self.infotext = "Runs all instances in a shared private network that only has loopback, so they find each other on 127.0.0.1 but can't reach your real network or the internet. Useful for LAN games that broadcast onto your network or try to connect online. Online play will not work with this enabled.".to_string();
+        }
+
+        let virtual_pads_check =
+            ui.checkbox(&mut self.options.virtual_pads, "Use virtual controllers");
+        if virtual_pads_check.hovered() {
+            self.infotext = "Gives each instance a virtual controller that PartyDeck feeds from its player's controller, so games only ever see their own player's pad. If a controller disconnects and reconnects, or its batteries are swapped, it's routed back to the same instance. Requires access to /dev/uinput.".to_string();
+        }
+
         let gamescope_lowres_fix_check = ui.checkbox(
             &mut self.options.gamescope_fix_lowres,
             "Automatically fix low resolution instances",
@@ -495,13 +634,11 @@ impl LightPartyApp {
         ui.separator();
 
         ui.horizontal(|ui| {
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_SOUTH.png")).max_height(12.0),
-            );
-            ui.label("[Z]");
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/MOUSE_RIGHT.png"))
-                    .max_height(12.0),
+            binding_hint(
+                ui,
+                &self.options.bindings,
+                UiAction::AddDevice,
+                &self.input_devices,
             );
             let add_text = match self.instance_add_dev {
                 None => "Add New Instance",
@@ -511,10 +648,12 @@ impl LightPartyApp {
 
             ui.add(egui::Separator::default().vertical());
 
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_EAST.png")).max_height(12.0),
+            binding_hint(
+                ui,
+                &self.options.bindings,
+                UiAction::RemoveDevice,
+                &self.input_devices,
             );
-            ui.label("[X]");
             let remove_text = match self.instance_add_dev {
                 None => "Remove",
                 Some(_) => "Cancel",
@@ -524,11 +663,12 @@ impl LightPartyApp {
             ui.add(egui::Separator::default().vertical());
 
             if self.instances.len() > 0 && self.instance_add_dev == None {
-                ui.add(
-                    egui::Image::new(egui::include_image!("../../res/BTN_NORTH.png"))
-                        .max_height(12.0),
+                binding_hint(
+                    ui,
+                    &self.options.bindings,
+                    UiAction::Invite,
+                    &self.input_devices,
                 );
-                ui.label("[A]");
                 ui.label("Invite to Instance");
             }
         });
@@ -579,13 +719,11 @@ impl LightPartyApp {
         if self.instances.len() > 0 {
             ui.separator();
             ui.horizontal(|ui| {
-                ui.add(
-                    egui::Image::new(egui::include_image!("../../res/BTN_START.png"))
-                        .max_height(16.0),
-                );
-                ui.add(
-                    egui::Image::new(egui::include_image!("../../res/BTN_START_PS5.png"))
-                        .max_height(16.0),
+                binding_hint(
+                    ui,
+                    &self.options.bindings,
+                    UiAction::Launch,
+                    &self.input_devices,
                 );
                 if ui.button("Start").clicked() {
                     self.prepare_game_launch();
@@ -1,10 +1,17 @@
+use crate::bindings::UiBindings;
+use crate::handler::Handler;
+use crate::input::DeviceInfo;
 use crate::paths::*;
+use crate::remap::RemapProfile;
+use crate::runner::RunnerType;
+use crate::sandbox::SandboxPolicy;
 
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub enum PadFilterType {
@@ -13,6 +20,22 @@ pub enum PadFilterType {
     OnlySteamInput,
 }
 
+// User settings for a single handler, keyed by handler uid
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct GameSettings {
+    // Empty uses the global Proton version
+    #[serde(default)]
+    pub proton_version: String,
+    #[serde(default)]
+    pub disable_protonfixes: bool,
+    // None uses the global runner
+    #[serde(default)]
+    pub runner: Option<RunnerType>,
+    // None uses the handler's policy
+    #[serde(default)]
+    pub sandbox: Option<SandboxPolicy>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PartyConfig {
     pub force_sdl: bool,
@@ -22,11 +45,41 @@ pub struct PartyConfig {
     pub kbm_support: bool,
     pub proton_version: String,
     pub proton_separate_pfxs: bool,
+    // Keys separate prefixes by profile instead of by instance slot
+    #[serde(default)]
+    pub proton_profile_pfxs: bool,
+    #[serde(default)]
+    pub runner: RunnerType,
+    #[serde(default)]
+    pub wine_path: String,
+    #[serde(default)]
+    pub wine_dxvk: bool,
+    #[serde(default)]
+    pub dxvk_path: String,
     #[serde(default)]
     pub vertical_two_player: bool,
     pub pad_filter_type: PadFilterType,
     #[serde(default)]
     pub allow_multiple_instances_on_same_device: bool,
+    // Runs a session's instances in a shared network namespace with only loopback
+    #[serde(default)]
+    pub isolated_network: bool,
+    // Gives instances virtual gamepads fed from their players' controllers
+    #[serde(default)]
+    pub virtual_pads: bool,
+    #[serde(default)]
+    pub remaps: Vec<RemapProfile>,
+    // Remap used for each controller model, keyed by DeviceInfo::model_key
+    #[serde(default)]
+    pub device_remaps: HashMap<String, String>,
+    // Remap used for each profile's controllers, taking priority over the model's
+    #[serde(default)]
+    pub profile_remaps: HashMap<String, String>,
+    #[serde(default)]
+    pub game_settings: HashMap<String, GameSettings>,
+    // What each of the launcher's controls is bound to
+    #[serde(default)]
+    pub bindings: UiBindings,
 }
 
 impl Default for PartyConfig {
@@ -39,9 +92,68 @@ impl Default for PartyConfig {
             kbm_support: true,
             proton_version: "".to_string(),
             proton_separate_pfxs: false,
+            proton_profile_pfxs: false,
+            runner: RunnerType::Umu,
+            wine_path: "".to_string(),
+            wine_dxvk: false,
+            dxvk_path: "".to_string(),
             vertical_two_player: false,
             pad_filter_type: PadFilterType::NoSteamInput,
             allow_multiple_instances_on_same_device: false,
+            isolated_network: false,
+            virtual_pads: false,
+            remaps: Vec::new(),
+            device_remaps: HashMap::new(),
+            profile_remaps: HashMap::new(),
+            game_settings: HashMap::new(),
+            bindings: UiBindings::default(),
+        }
+    }
+}
+
+impl PartyConfig {
+    pub fn game_settings(&self, uid: &str) -> GameSettings {
+        self.game_settings.get(uid).cloned().unwrap_or_default()
+    }
+
+    pub fn runner_for(&self, uid: &str) -> RunnerType {
+        self.game_settings
+            .get(uid)
+            .and_then(|settings| settings.runner)
+            .unwrap_or(self.runner)
+    }
+
+    // Whether Wine runs anything, globally or for a game that overrides the runner
+    pub fn uses_wine(&self) -> bool {
+        self.runner == RunnerType::Wine
+            || self
+                .game_settings
+                .values()
+                .any(|settings| settings.runner == Some(RunnerType::Wine))
+    }
+
+    pub fn sandbox_for(&self, h: &Handler) -> SandboxPolicy {
+        self.game_settings
+            .get(&h.uid)
+            .and_then(|settings| settings.sandbox)
+            .or(h.sandbox)
+            .unwrap_or_default()
+    }
+
+    // The remap for a player's controller, if any
+    pub fn remap_for(&self, profname: &str, dev: &DeviceInfo) -> Option<&RemapProfile> {
+        let name = self
+            .profile_remaps
+            .get(profname)
+            .or_else(|| self.device_remaps.get(&dev.model_key()))?;
+        self.remaps.iter().find(|remap| &remap.name == name)
+    }
+
+    // The Proton version to use for a handler, taking its pin into account
+    pub fn proton_version_for(&self, uid: &str) -> &str {
+        match self.game_settings.get(uid) {
+            Some(settings) if !settings.proton_version.is_empty() => &settings.proton_version,
+            _ => &self.proton_version,
         }
     }
 }
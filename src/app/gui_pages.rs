@@ -1,13 +1,22 @@
 use super::app::{MenuPage, PartyApp, SettingsPage};
 use super::config::*;
+use crate::bindings::*;
 use crate::game::Game::*;
 use crate::input::*;
 use crate::paths::*;
+use crate::prefix::*;
+use crate::proton::*;
+use crate::remap::*;
+use crate::runner::RunnerType;
+use crate::runtime::{runtime_label, runtimes_summary};
+use crate::sandbox::ALL_POLICIES;
+use crate::session::*;
 use crate::util::*;
 
 use dialog::DialogBox;
 use eframe::egui::RichText;
 use eframe::egui::{self, Ui};
+use std::path::PathBuf;
 
 macro_rules! cur_game {
     ($self:expr) => {
@@ -19,7 +28,13 @@ impl PartyApp {
     pub fn display_page_main(&mut self, ui: &mut Ui) {
         ui.heading("Welcome to PartyDeck");
         ui.separator();
-        ui.label("Press SELECT/BACK or Tab to unlock gamepad navigation.");
+        let unlock = self
+            .options
+            .bindings
+            .hint(UiAction::NextWidget, &self.input_devices);
+        ui.label(format!(
+            "Press {unlock} or Tab to unlock gamepad navigation."
+        ));
         ui.hyperlink_to(
             "Download game handlers here",
             "https://drive.proton.me/urls/D9HBKM18YR#zG8XC8yVy9WL",
@@ -42,12 +57,32 @@ impl PartyApp {
                 SettingsPage::Gamescope,
                 "Gamescope",
             );
+            if ui
+                .selectable_value(&mut self.settings_page, SettingsPage::Prefixes, "Prefixes")
+                .clicked()
+            {
+                self.prefixes = scan_prefixes();
+            }
+            if ui
+                .selectable_value(
+                    &mut self.settings_page,
+                    SettingsPage::Remapping,
+                    "Remapping",
+                )
+                .clicked()
+            {
+                self.profiles = scan_profiles(false);
+            }
+            ui.selectable_value(&mut self.settings_page, SettingsPage::Controls, "Controls");
         });
         ui.separator();
 
         match self.settings_page {
             SettingsPage::General => self.display_settings_general(ui),
             SettingsPage::Gamescope => self.display_settings_gamescope(ui),
+            SettingsPage::Prefixes => self.display_settings_prefixes(ui),
+            SettingsPage::Remapping => self.display_settings_remapping(ui),
+            SettingsPage::Controls => self.display_settings_controls(ui),
         }
 
         ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
@@ -114,12 +149,11 @@ impl PartyApp {
         ui.separator();
 
         ui.horizontal(|ui| {
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_START.png")).max_height(16.0),
-            );
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_START_PS5.png"))
-                    .max_height(16.0),
+            binding_hint(
+                ui,
+                &self.options.bindings,
+                UiAction::Play,
+                &self.input_devices,
             );
             if ui.button("Play").clicked() {
                 self.instances.clear();
@@ -130,9 +164,25 @@ impl PartyApp {
             if let HandlerRef(h) = cur_game!(self) {
                 ui.add(egui::Separator::default().vertical());
                 if h.win {
-                    ui.label(" Proton");
+                    ui.label(format!(" {}", self.options.runner_for(&h.uid).display()));
                 } else {
                     ui.label("🐧 Native");
+                    if !h.runtime.is_empty() {
+                        ui.add(egui::Separator::default().vertical());
+                        if self
+                            .runtime_info
+                            .as_ref()
+                            .is_none_or(|(uid, ..)| *uid != h.uid)
+                        {
+                            self.runtime_info =
+                                Some((h.uid.clone(), runtime_label(h), runtimes_summary()));
+                        }
+                        if let Some((_, label, summary)) = &self.runtime_info {
+                            ui.label(format!("Runtime: {label}")).on_hover_ui(|ui| {
+                                ui.label(summary);
+                            });
+                        }
+                    }
                 }
                 ui.add(egui::Separator::default().vertical());
                 ui.label(format!("Author: {}", h.author));
@@ -141,6 +191,95 @@ impl PartyApp {
             }
         });
 
+        if let HandlerRef(h) = cur_game!(self) {
+            let uid = h.uid.clone();
+            let mut settings = self.options.game_settings(&uid);
+            if h.win {
+                ui.horizontal(|ui| {
+                    let runner_text = match settings.runner {
+                        Some(runner) => runner.display(),
+                        None => "Default (global setting)",
+                    };
+                    ui.label("Runner");
+                    let combo = egui::ComboBox::from_id_salt("game_runner")
+                        .selected_text(runner_text)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut settings.runner,
+                                None,
+                                "Default (global setting)",
+                            );
+                            for runner in [RunnerType::Umu, RunnerType::Wine] {
+                                ui.selectable_value(
+                                    &mut settings.runner,
+                                    Some(runner),
+                                    runner.display(),
+                                );
+                            }
+                        })
+                        .response;
+                    if combo.hovered() {
+                        self.infotext = "Selects how this game is run, overriding the global setting.".to_string();
+                    }
+                });
+                let runner = settings.runner.unwrap_or(self.options.runner);
+                ui.add_enabled_ui(runner == RunnerType::Umu, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Proton version");
+                        let combo = proton_version_combo(
+                            ui,
+                            "game_proton_version",
+                            &mut settings.proton_version,
+                            &self.proton_builds,
+                            "Default (global setting)",
+                        );
+                        if combo.hovered() {
+                            self.infotext = "Pins this game to a specific Proton build, overriding the global setting.".to_string();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let gameid = h.umu_gameid().unwrap_or("umu-default".to_string());
+                        let store = h.umu_store().unwrap_or("none".to_string());
+                        let id_label = ui.label(format!("umu ID: {gameid} (store: {store})"));
+                        ui.add(egui::Separator::default().vertical());
+                        let fixes_check =
+                            ui.checkbox(&mut settings.disable_protonfixes, "Disable protonfixes");
+                        if id_label.hovered() || fixes_check.hovered() {
+                            self.infotext = "umu looks up compatibility fixes (protonfixes) for this game by its ID, the same fixes Steam would apply. If a fix causes problems with split-screen, you can disable them for this game.".to_string();
+                        }
+                    });
+                });
+            }
+            ui.horizontal(|ui| {
+                let default_text = format!(
+                    "Default ({})",
+                    h.sandbox.unwrap_or_default().display()
+                );
+                let sandbox_text = match settings.sandbox {
+                    Some(policy) => policy.display().to_string(),
+                    None => default_text.clone(),
+                };
+                ui.label("Sandbox");
+                let combo = egui::ComboBox::from_id_salt("game_sandbox")
+                    .selected_text(sandbox_text)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut settings.sandbox, None, default_text);
+                        for policy in ALL_POLICIES {
+                            ui.selectable_value(
+                                &mut settings.sandbox,
+                                Some(policy),
+                                policy.display(),
+                            );
+                        }
+                    })
+                    .response;
+                if combo.hovered() {
+                    self.infotext = "Limits what the game can access. Permissive gives the game full access to your files. Read-only host lets the game write only to its own folder, PartyDeck's data and caches. Private home additionally hides the rest of your home folder. Stricter policies protect your files from untrusted handlers and games, but may break games that expect more access.".to_string();
+                }
+            });
+            self.set_game_settings(&uid, settings);
+        }
+
         if let HandlerRef(h) = cur_game!(self) {
             egui::ScrollArea::horizontal()
                 .max_width(f32::INFINITY)
@@ -163,30 +302,45 @@ impl PartyApp {
     }
 
     pub fn display_page_instances(&mut self, ui: &mut Ui) {
+        let session = session_info();
+
         ui.heading("Instances");
         ui.separator();
 
+        if let Some(info) = &session {
+            ui.label(format!("Joining the running session of {}", info.game_name));
+            for (i, player) in info.players.iter().enumerate() {
+                ui.label(RichText::new(format!("Instance {}: {} (running)", i + 1, player)).weak());
+            }
+            ui.separator();
+        }
+        let running = session.as_ref().map_or(0, |info| info.players.len());
+        let is_handler = match &session {
+            Some(info) => info.is_handler,
+            None => matches!(cur_game!(self), HandlerRef(_)),
+        };
+
         ui.horizontal(|ui| {
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_SOUTH.png")).max_height(12.0),
-            );
-            ui.label("[Z]");
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/MOUSE_RIGHT.png"))
-                    .max_height(12.0),
+            binding_hint(
+                ui,
+                &self.options.bindings,
+                UiAction::AddDevice,
+                &self.input_devices,
             );
             let add_text = match self.instance_add_dev {
                 None => "Add New Instance",
-                Some(i) => &format!("Add to Instance {}", i + 1),
+                Some(i) => &format!("Add to Instance {}", running + i + 1),
             };
             ui.label(add_text);
 
             ui.add(egui::Separator::default().vertical());
 
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_EAST.png")).max_height(12.0),
+            binding_hint(
+                ui,
+                &self.options.bindings,
+                UiAction::RemoveDevice,
+                &self.input_devices,
             );
-            ui.label("[X]");
             let remove_text = match self.instance_add_dev {
                 None => "Remove",
                 Some(_) => "Cancel",
@@ -196,11 +350,12 @@ impl PartyApp {
             ui.add(egui::Separator::default().vertical());
 
             if self.instances.len() > 0 && self.instance_add_dev == None {
-                ui.add(
-                    egui::Image::new(egui::include_image!("../../res/BTN_NORTH.png"))
-                        .max_height(12.0),
+                binding_hint(
+                    ui,
+                    &self.options.bindings,
+                    UiAction::Invite,
+                    &self.input_devices,
                 );
-                ui.label("[A]");
                 ui.label("Invite to Instance");
             }
         });
@@ -210,9 +365,9 @@ impl PartyApp {
         let mut devices_to_remove: Vec<(usize,usize)> = Vec::new();
         for (i, instance) in &mut self.instances.iter_mut().enumerate() {
             ui.horizontal(|ui| {
-                ui.label(format!("Instance {}", i + 1));
+                ui.label(format!("Instance {}", running + i + 1));
 
-                if let HandlerRef(_) = cur_game!(self) {
+                if is_handler {
                     ui.label("👤");
                     egui::ComboBox::from_id_salt(format!("{i}")).show_index(
                         ui,
@@ -261,15 +416,17 @@ impl PartyApp {
         if self.instances.len() > 0 {
             ui.separator();
             ui.horizontal(|ui| {
-                ui.add(
-                    egui::Image::new(egui::include_image!("../../res/BTN_START.png"))
-                        .max_height(16.0),
-                );
-                ui.add(
-                    egui::Image::new(egui::include_image!("../../res/BTN_START_PS5.png"))
-                        .max_height(16.0),
+                binding_hint(
+                    ui,
+                    &self.options.bindings,
+                    UiAction::Launch,
+                    &self.input_devices,
                 );
-                if ui.button("Start").clicked() {
+                if session.is_some() {
+                    if ui.button("Join Session").clicked() {
+                        self.prepare_session_join();
+                    }
+                } else if ui.button("Start").clicked() {
                     self.prepare_game_launch();
                 }
             });
@@ -331,16 +488,62 @@ impl PartyApp {
         });
 
         ui.horizontal(|ui| {
-        let proton_ver_label = ui.label("Proton version");
-        let proton_ver_editbox = ui.add(
-            egui::TextEdit::singleline(&mut self.options.proton_version)
-                .hint_text("GE-Proton"),
-        );
-        if proton_ver_label.hovered() || proton_ver_editbox.hovered() {
-            self.infotext = "Specify a Proton version. This can be a path, e.g. \"/path/to/proton\" or just a name, e.g. \"GE-Proton\" for the latest version of Proton-GE. If left blank, this will default to \"GE-Proton\". If unsure, leave this blank.".to_string();
-        }
+            let proton_ver_label = ui.label("Proton version");
+            let proton_ver_combo = proton_version_combo(
+                ui,
+                "proton_version",
+                &mut self.options.proton_version,
+                &self.proton_builds,
+                "Default (latest GE-Proton)",
+            );
+            if ui.button("🔄").clicked() {
+                self.proton_builds = scan_proton_builds();
+            }
+            if proton_ver_label.hovered() || proton_ver_combo.hovered() {
+                self.infotext = "Select the Proton build used to run Windows games. Builds are found in Steam's compatibilitytools.d folder, your Steam libraries and umu's cache. \"GE-Proton\" and \"UMU-Proton\" are downloaded automatically by umu if they aren't installed. If unsure, leave this on the default.".to_string();
+            }
         });
 
+        ui.horizontal(|ui| {
+            let runner_label = ui.label("Windows runner");
+            let r1 = ui.radio_value(
+                &mut self.options.runner,
+                RunnerType::Umu,
+                RunnerType::Umu.display(),
+            );
+            let r2 = ui.radio_value(
+                &mut self.options.runner,
+                RunnerType::Wine,
+                RunnerType::Wine.display(),
+            );
+            if runner_label.hovered() || r1.hovered() || r2.hovered() {
+                self.infotext = "Select how Windows games are run. Proton (umu) runs games through umu-launcher with the selected Proton build. Wine runs games with a system Wine, Wine-GE or Lutris runner of your choice. Individual games can override this on their page. If unsure, leave this on Proton.".to_string();
+            }
+        });
+
+        if self.options.uses_wine() {
+            ui.horizontal(|ui| {
+                let wine_label = ui.label("Wine binary");
+                let wine_editbox = ui.add(
+                    egui::TextEdit::singleline(&mut self.options.wine_path).hint_text("wine"),
+                );
+                if wine_label.hovered() || wine_editbox.hovered() {
+                    self.infotext = "Path to the wine binary used for games run with Wine, whether by default or on their own page, e.g. \"~/.local/share/lutris/runners/wine/wine-ge-8-26-x86_64/bin/wine\". If left blank, the wine found in your PATH is used.".to_string();
+                }
+            });
+            ui.horizontal(|ui| {
+                let dxvk_check = ui.checkbox(&mut self.options.wine_dxvk, "Install DXVK");
+                let dxvk_editbox = ui.add_enabled(
+                    self.options.wine_dxvk,
+                    egui::TextEdit::singleline(&mut self.options.dxvk_path)
+                        .hint_text("/path/to/dxvk"),
+                );
+                if dxvk_check.hovered() || dxvk_editbox.hovered() {
+                    self.infotext = "Installs DXVK into each Wine prefix before launching. Point this to an extracted DXVK release folder containing the x64 and x32 folders.".to_string();
+                }
+            });
+        }
+
         let proton_separate_pfxs_check = ui.checkbox(
             &mut self.options.proton_separate_pfxs,
             "Run instances in separate Proton prefixes",
@@ -348,6 +551,17 @@ impl PartyApp {
         if proton_separate_pfxs_check.hovered() {
             self.infotext = "Runs each instance in its own Proton prefix. If unsure, leave this unchecked. This option will take up more space on the disk, but may also help with certain Proton-related issues such as only one instance of a game starting.".to_string();
         }
+
+        let proton_profile_pfxs_check = ui.add_enabled(
+            self.options.proton_separate_pfxs,
+            egui::Checkbox::new(
+                &mut self.options.proton_profile_pfxs,
+                "Give each profile its own prefix",
+            ),
+        );
+        if proton_profile_pfxs_check.hovered() {
+            self.infotext = "Keeps a separate prefix for each profile instead of each instance slot, so registry settings and other data games keep in the prefix follow the player. Guests get a fresh prefix that is erased when the session ends.".to_string();
+        }
         
         let allow_multiple_instances_on_same_device_check = ui.checkbox(
             &mut self.options.allow_multiple_instances_on_same_device,
@@ -357,23 +571,23 @@ impl PartyApp {
             self.infotext = "Allow multiple instances on the same device. This can be useful for testing or when one person wants to control multiple instances.".to_string();
         }
 
-        ui.separator();
+        let isolated_network_check = ui.checkbox(
+            &mut self.options.isolated_network,
+            "Isolate instances from the network",
+        );
+        if isolated_network_check.hovered() {
+            self.infotext = "Runs all instances in a shared private network that only has loopback, so they find each other on 127.0.0.1 but can't reach your real network or the internet. Useful for LAN games that broadcast onto your network or try to connect online. Online play will not work with this enabled.".to_string();
+        }
 
-        ui.horizontal(|ui| {
-        if ui.button("Erase Proton Prefix").clicked() {
-            if yesno("Erase Prefix?", "This will erase the Wine prefix used by PartyDeck. This shouldn't erase profile/game-specific data, but exercise caution. Are you sure?") && PATH_PARTY.join("gamesyms").exists() {
-                if let Err(err) = std::fs::remove_dir_all(PATH_PARTY.join("pfx")) {
-                    msg("Error", &format!("Couldn't erase pfx data: {}", err));
-                }
-                else if let Err(err) = std::fs::create_dir_all(PATH_PARTY.join("pfx")) {
-                    msg("Error", &format!("Couldn't re-create pfx directory: {}", err));
-                }
-                else {
-                    msg("Data Erased", "Proton prefix data successfully erased.");
-                }
-            }
+        let virtual_pads_check =
+            ui.checkbox(&mut self.options.virtual_pads, "Use virtual controllers");
+        if virtual_pads_check.hovered() {
+            self.infotext = "Gives each instance a virtual controller that PartyDeck feeds from its player's controller, so games only ever see their own player's pad. If a controller disconnects and reconnects, or its batteries are swapped, it's routed back to the same instance. Requires access to /dev/uinput.".to_string();
         }
 
+        ui.separator();
+
+        ui.horizontal(|ui| {
         if ui.button("Erase Symlink Data").clicked() {
             if yesno("Erase Symlink Data?", "This will erase all game symlink data. This shouldn't erase profile/game-specific data, but exercise caution. Are you sure?") && PATH_PARTY.join("gamesyms").exists() {
                 if let Err(err) = std::fs::remove_dir_all(PATH_PARTY.join("gamesyms")) {
@@ -435,4 +649,460 @@ impl PartyApp {
             self.infotext = "Runs a custom Gamescope build with support for holding keyboards and mice. If you want to use your own Gamescope installation, uncheck this.".to_string();
         }
     }
+
+    pub fn display_settings_prefixes(&mut self, ui: &mut Ui) {
+        ui.label("Separate instance prefixes are cloned from the template prefix, which is set up once per Proton version. Resetting a prefix erases it, and it will be rebuilt the next time it's used.");
+        ui.separator();
+
+        let running = session_running();
+        let mut reset: Option<(String, PathBuf)> = None;
+        egui::Grid::new("prefixes")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Prefix");
+                ui.strong("Version");
+                ui.strong("Size");
+                ui.label("");
+                ui.end_row();
+                for pfx in &self.prefixes {
+                    ui.label(&pfx.name);
+                    ui.label(&pfx.version);
+                    match prefix_size(&pfx.path) {
+                        Some(size) => ui.label(format_size(size)),
+                        None => {
+                            ui.ctx()
+                                .request_repaint_after(std::time::Duration::from_millis(250));
+                            ui.label("…")
+                        }
+                    };
+                    if ui
+                        .add_enabled(!running, egui::Button::new("Reset"))
+                        .clicked()
+                    {
+                        reset = Some((pfx.name.clone(), pfx.path.clone()));
+                    }
+                    ui.end_row();
+                }
+            });
+        if self.prefixes.is_empty() {
+            ui.label("No prefixes have been created yet.");
+        }
+
+        if let Some((name, path)) = reset {
+            if yesno("Reset Prefix?", &format!("This will erase the prefix {name}. This shouldn't erase profile/game-specific data, but exercise caution. Are you sure?")) {
+                if let Err(err) = reset_prefix(&path) {
+                    msg("Error", &format!("Couldn't erase prefix: {}", err));
+                }
+            }
+            self.prefixes = scan_prefixes();
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("🔄 Refresh").clicked() {
+                self.prefixes = scan_prefixes();
+            }
+            if ui.add_enabled(!running, egui::Button::new("Reset All Prefixes")).clicked()
+                && yesno("Reset All Prefixes?", "This will erase every Wine prefix used by PartyDeck. This shouldn't erase profile/game-specific data, but exercise caution. Are you sure?")
+            {
+                for pfx in &self.prefixes {
+                    if let Err(err) = reset_prefix(&pfx.path) {
+                        msg("Error", &format!("Couldn't erase prefix {}: {}", pfx.name, err));
+                    }
+                }
+                self.prefixes = scan_prefixes();
+            }
+        });
+    }
+
+    pub fn display_settings_remapping(&mut self, ui: &mut Ui) {
+        ui.label("Remaps change what a controller's buttons and sticks do before the game sees them. Remapped controllers are given a virtual controller during sessions, even if virtual controllers are turned off.");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let selected_text = self
+                .options
+                .remaps
+                .get(self.selected_remap)
+                .map_or("No remaps", |remap| remap.name.as_str());
+            egui::ComboBox::from_id_salt("remap_select")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for (i, remap) in self.options.remaps.iter().enumerate() {
+                        ui.selectable_value(&mut self.selected_remap, i, &remap.name);
+                    }
+                });
+            if ui.button("New").clicked() {
+                if let Some(name) = dialog::Input::new("Enter remap name:")
+                    .title("New Remap")
+                    .show()
+                    .expect("Could not display dialog box")
+                {
+                    let name = name.trim().to_string();
+                    if name.is_empty() || self.options.remaps.iter().any(|r| r.name == name) {
+                        msg("Error", "Remap names must be unique and not empty.");
+                    } else {
+                        self.options.remaps.push(RemapProfile {
+                            name,
+                            ..Default::default()
+                        });
+                        self.selected_remap = self.options.remaps.len() - 1;
+                    }
+                }
+            }
+            if self.selected_remap < self.options.remaps.len()
+                && ui.button("Delete").clicked()
+                && yesno("Delete Remap?", "Controllers and profiles using this remap will go back to their default controls. Are you sure?")
+            {
+                let removed = self.options.remaps.remove(self.selected_remap);
+                self.options.device_remaps.retain(|_, name| *name != removed.name);
+                self.options.profile_remaps.retain(|_, name| *name != removed.name);
+                self.selected_remap = 0;
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 48.0)
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                if let Some(remap) = self.options.remaps.get_mut(self.selected_remap) {
+                    ui.separator();
+                    remap_editor(ui, remap);
+                }
+                ui.separator();
+                self.remap_assignments(ui);
+            });
+    }
+
+    pub fn display_settings_controls(&mut self, ui: &mut Ui) {
+        ui.label("The buttons and keys that control PartyDeck. Menu controls work on every page except Instances, which has its own. Click a binding to remove it.");
+        if ui.button("Restore Default Controls").clicked() {
+            self.options.bindings = UiBindings::default();
+            self.binding_capture = None;
+        }
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 48.0)
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                for (heading, actions) in [
+                    ("Menus", &MENU_ACTIONS[..]),
+                    ("Instances page", &INSTANCE_ACTIONS[..]),
+                ] {
+                    ui.strong(heading);
+                    egui::Grid::new(format!("bindings_{heading}"))
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            for action in actions {
+                                ui.label(action.label());
+                                ui.horizontal(|ui| {
+                                    let codes = self.options.bindings.codes(*action);
+                                    // Players couldn't join or start a session without these
+                                    let last =
+                                        codes.len() == 1 && INSTANCE_ACTIONS.contains(action);
+                                    for code in codes {
+                                        let binding = ui.add_enabled(
+                                            !last,
+                                            egui::Button::new(binding_label(code)),
+                                        );
+                                        if last && binding.hovered() {
+                                            self.infotext = "Actions on the Instances page need at least one binding. Add another before removing this one.".to_string();
+                                        }
+                                        if binding.clicked() {
+                                            self.options.bindings.unbind(*action, code);
+                                        }
+                                    }
+                                    if self.binding_capture == Some(*action) {
+                                        ui.label("Press a button or key...");
+                                        if ui.button("Cancel").clicked() {
+                                            self.binding_capture = None;
+                                        }
+                                    } else if ui.button("➕").clicked() {
+                                        self.binding_capture = Some(*action);
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                        });
+
+                    // A press only triggers the first action it's bound to
+                    let mut shared: Vec<(evdev::KeyCode, Vec<UiAction>)> = Vec::new();
+                    for action in actions {
+                        for code in self.options.bindings.codes(*action) {
+                            match shared.iter_mut().find(|(c, _)| *c == code) {
+                                Some((_, bound)) => bound.push(*action),
+                                None => shared.push((code, vec![*action])),
+                            }
+                        }
+                    }
+                    for (code, bound) in shared.iter().filter(|(_, bound)| bound.len() > 1) {
+                        let labels: Vec<&str> = bound.iter().map(|action| action.label()).collect();
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!(
+                                "⚠ {} is bound to {}, so it only does \"{}\"",
+                                binding_label(*code),
+                                labels.join(", "),
+                                labels[0]
+                            ),
+                        );
+                    }
+                    ui.separator();
+                }
+            });
+    }
+
+    // Which remap each connected controller model and each profile uses
+    fn remap_assignments(&mut self, ui: &mut Ui) {
+        let names: Vec<String> = self.options.remaps.iter().map(|r| r.name.clone()).collect();
+
+        ui.strong("Controller models");
+        let mut models: Vec<(String, String)> = Vec::new();
+        for dev in &self.input_devices {
+            let key = dev.info().model_key();
+            if dev.device_type() == DeviceType::Gamepad && !models.iter().any(|(k, _)| *k == key) {
+                models.push((key, dev.name().to_string()));
+            }
+        }
+        // Models with a saved remap stay listed while they're unplugged
+        let mut saved: Vec<&String> = self.options.device_remaps.keys().collect();
+        saved.sort();
+        for key in saved {
+            if !models.iter().any(|(k, _)| k == key) {
+                models.push((key.clone(), "Not connected".to_string()));
+            }
+        }
+        egui::Grid::new("remap_models")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (key, name) in &models {
+                    ui.label(format!("{name} ({key})"));
+                    remap_assignment_combo(ui, key, &mut self.options.device_remaps, key, &names);
+                    ui.end_row();
+                }
+            });
+        if models.is_empty() {
+            ui.label("No controllers are connected.");
+        }
+
+        ui.strong("Profiles");
+        ui.label("A controller shared by several players uses the remap of the first player it's assigned to.");
+        egui::Grid::new("remap_profiles")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for profile in &self.profiles {
+                    ui.label(profile);
+                    let id = format!("profile_{profile}");
+                    remap_assignment_combo(
+                        ui,
+                        &id,
+                        &mut self.options.profile_remaps,
+                        profile,
+                        &names,
+                    );
+                    ui.end_row();
+                }
+            });
+        if self.profiles.is_empty() {
+            ui.label("No profiles have been created yet.");
+        }
+    }
+}
+
+// Edits what each button and axis of a remap is sent as, along with inversion and deadzones
+fn remap_editor(ui: &mut Ui, remap: &mut RemapProfile) {
+    ui.strong("Buttons");
+    egui::Grid::new("remap_buttons")
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (code, label) in &GAMEPAD_BUTTONS {
+                let from = button_name(*code);
+                let mut to = remap.buttons.get(&from).cloned().unwrap_or(from.clone());
+                ui.label(*label);
+                egui::ComboBox::from_id_salt(format!("remap_button_{from}"))
+                    .selected_text(button_label(&to))
+                    .show_ui(ui, |ui| {
+                        for (code, label) in &GAMEPAD_BUTTONS {
+                            ui.selectable_value(&mut to, button_name(*code), *label);
+                        }
+                    });
+                match to == from {
+                    true => remap.buttons.remove(&from),
+                    false => remap.buttons.insert(from, to),
+                };
+                ui.end_row();
+            }
+        });
+
+    ui.strong("Axes");
+    egui::Grid::new("remap_axes").num_columns(3).show(ui, |ui| {
+        for (code, label) in &GAMEPAD_AXES {
+            let from = axis_name(*code);
+            let mut to = remap.axes.get(&from).cloned().unwrap_or(from.clone());
+            ui.label(*label);
+            egui::ComboBox::from_id_salt(format!("remap_axis_{from}"))
+                .selected_text(axis_label(&to))
+                .show_ui(ui, |ui| {
+                    for (code, label) in &GAMEPAD_AXES {
+                        ui.selectable_value(&mut to, axis_name(*code), *label);
+                    }
+                });
+            let mut inverted = remap.inverted.contains(&from);
+            ui.checkbox(&mut inverted, "Invert");
+            remap.inverted.retain(|axis| *axis != from);
+            if inverted {
+                remap.inverted.push(from.clone());
+            }
+            match to == from {
+                true => remap.axes.remove(&from),
+                false => remap.axes.insert(from, to),
+            };
+            ui.end_row();
+        }
+    });
+
+    ui.strong("Deadzones");
+    ui.add(egui::Slider::new(&mut remap.left_deadzone, 0.0..=0.9).text("Left stick"));
+    ui.add(egui::Slider::new(&mut remap.right_deadzone, 0.0..=0.9).text("Right stick"));
+}
+
+fn button_label(name: &str) -> &str {
+    GAMEPAD_BUTTONS
+        .iter()
+        .find(|(code, _)| button_name(*code) == name)
+        .map_or(name, |(_, label)| label)
+}
+
+fn axis_label(name: &str) -> &str {
+    GAMEPAD_AXES
+        .iter()
+        .find(|(code, _)| axis_name(*code) == name)
+        .map_or(name, |(_, label)| label)
+}
+
+// Dropdown for picking the remap stored under key in assignments; "None" removes it
+fn remap_assignment_combo(
+    ui: &mut Ui,
+    id: &str,
+    assignments: &mut std::collections::HashMap<String, String>,
+    key: &str,
+    names: &[String],
+) {
+    let mut value = assignments.get(key).cloned().unwrap_or_default();
+    egui::ComboBox::from_id_salt(format!("remap_assign_{id}"))
+        .selected_text(match value.is_empty() {
+            true => "None",
+            false => value.as_str(),
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut value, String::new(), "None");
+            for name in names {
+                ui.selectable_value(&mut value, name.clone(), name);
+            }
+        });
+    match value.is_empty() {
+        true => assignments.remove(key),
+        false => assignments.insert(key.to_string(), value),
+    };
+}
+
+// Shows what to press for an action, e.g. "[A / ✖]", going by the controllers connected
+pub fn binding_hint(ui: &mut Ui, bindings: &UiBindings, action: UiAction, devices: &[InputDevice]) {
+    let hint = bindings.hint(action, devices);
+    if !hint.is_empty() {
+        ui.label(RichText::new(format!("[{hint}]")).strong());
+    }
+}
+
+// Shows connect/disconnect messages in the corner for a few seconds each
+pub fn show_toasts(ctx: &egui::Context, toasts: &mut Vec<(String, std::time::Instant)>) {
+    toasts.retain(|(_, since)| since.elapsed() < std::time::Duration::from_secs(3));
+    if toasts.is_empty() {
+        return;
+    }
+    egui::Area::new("toasts".into())
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            for (text, _) in toasts.iter() {
+                egui::Frame::NONE
+                    .fill(egui::Color32::from_rgba_premultiplied(0, 0, 0, 192))
+                    .corner_radius(6.0)
+                    .inner_margin(egui::Margin::symmetric(12, 8))
+                    .show(ui, |ui| {
+                        ui.label(text);
+                    });
+                ui.add_space(4.0);
+            }
+        });
+    // Keep redrawing so they go away on time
+    ctx.request_repaint_after(std::time::Duration::from_millis(250));
+}
+
+// Dropdown for picking a Proton build; an empty value means the given default
+pub fn proton_version_combo(
+    ui: &mut Ui,
+    id: &str,
+    value: &mut String,
+    builds: &[ProtonBuild],
+    default_text: &str,
+) -> egui::Response {
+    // Anything that isn't a known build is a path to a Proton folder, typed in next to the
+    // dropdown; picking "Custom path..." shows the field before anything is typed
+    let custom_id = egui::Id::new(id).with("custom");
+    let known = value.is_empty()
+        || UMU_PROTON_NAMES.contains(&value.as_str())
+        || builds.iter().any(|b| b.name == *value);
+    let mut custom = !known || ui.data(|d| d.get_temp(custom_id).unwrap_or(false));
+
+    let selected_text = match builds.iter().find(|b| b.name == *value) {
+        _ if custom => "Custom path".to_string(),
+        _ if value.is_empty() => default_text.to_string(),
+        Some(build) => build.display(),
+        None => value.clone(),
+    };
+    let mut response = egui::ComboBox::from_id_salt(id)
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            let mut picked = |ui: &mut Ui, item: String, text: String| {
+                if ui
+                    .selectable_label(!custom && *value == item, text)
+                    .clicked()
+                {
+                    *value = item;
+                    custom = false;
+                }
+            };
+            picked(ui, String::new(), default_text.to_string());
+            for name in UMU_PROTON_NAMES {
+                picked(ui, name.to_string(), format!("{name} (latest)"));
+            }
+            for build in builds {
+                picked(ui, build.name.clone(), build.display());
+            }
+            if ui.selectable_label(custom, "Custom path...").clicked() && !custom {
+                value.clear();
+                custom = true;
+            }
+        })
+        .response;
+    ui.data_mut(|d| d.insert_temp(custom_id, custom));
+
+    if custom {
+        response |= ui.add(
+            egui::TextEdit::singleline(value)
+                .hint_text("/path/to/proton")
+                .desired_width(200.0),
+        );
+    }
+    response
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GB", b as f64 / (1u64 << 30) as f64),
+        b => format!("{:.0} MB", b as f64 / (1u64 << 20) as f64),
+    }
 }
@@ -1,7 +1,10 @@
 use super::app::{MenuPage, PartyApp};
+use super::gui_pages::binding_hint;
+use crate::bindings::UiAction;
 use crate::game::{Game::*, *};
 use crate::input::*;
 use crate::paths::*;
+use crate::session::*;
 use crate::util::*;
 
 use eframe::egui::RichText;
@@ -16,16 +19,25 @@ macro_rules! cur_game {
 impl PartyApp {
     pub fn display_panel_top(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_EAST.png")).max_height(12.0),
+            binding_hint(
+                ui,
+                &self.options.bindings,
+                UiAction::Home,
+                &self.input_devices,
             );
             ui.selectable_value(&mut self.cur_page, MenuPage::Home, "Home");
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_NORTH.png")).max_height(12.0),
+            binding_hint(
+                ui,
+                &self.options.bindings,
+                UiAction::Settings,
+                &self.input_devices,
             );
             ui.selectable_value(&mut self.cur_page, MenuPage::Settings, "Settings");
-            ui.add(
-                egui::Image::new(egui::include_image!("../../res/BTN_WEST.png")).max_height(12.0),
+            binding_hint(
+                ui,
+                &self.options.bindings,
+                UiAction::Profiles,
+                &self.input_devices,
             );
             if ui
                 .selectable_value(&mut self.cur_page, MenuPage::Profiles, "Profiles")
@@ -40,6 +52,18 @@ impl PartyApp {
                 self.input_devices = scan_input_devices(&self.options.pad_filter_type);
             }
 
+            if session_running() {
+                if ui.button("➕ Add Player").clicked() {
+                    self.instances.clear();
+                    self.profiles = scan_profiles(true);
+                    self.instance_add_dev = None;
+                    self.cur_page = MenuPage::Instances;
+                }
+                if ui.button("⏹ End Session").clicked() {
+                    self.end_session();
+                }
+            }
+
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if ui.button("❌ Quit").clicked() {
                     ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
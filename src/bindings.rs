@@ -0,0 +1,231 @@
+use crate::input::InputDevice;
+use crate::remap::GAMEPAD_BUTTONS;
+
+use evdev::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// Things the launcher's controls can do
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UiAction {
+    Confirm,
+    Home,
+    Profiles,
+    Settings,
+    NextWidget,
+    Play,
+    PrevTab,
+    NextTab,
+    AddDevice,
+    RemoveDevice,
+    Invite,
+    Launch,
+}
+
+// Actions available while browsing the menus
+pub static MENU_ACTIONS: [UiAction; 8] = [
+    UiAction::Confirm,
+    UiAction::Home,
+    UiAction::Profiles,
+    UiAction::Settings,
+    UiAction::NextWidget,
+    UiAction::Play,
+    UiAction::PrevTab,
+    UiAction::NextTab,
+];
+
+// Actions available on the Instances page, where each device acts for its own player
+pub static INSTANCE_ACTIONS: [UiAction; 4] = [
+    UiAction::AddDevice,
+    UiAction::RemoveDevice,
+    UiAction::Invite,
+    UiAction::Launch,
+];
+
+impl UiAction {
+    pub fn label(&self) -> &str {
+        match self {
+            UiAction::Confirm => "Select",
+            UiAction::Home => "Go to Home",
+            UiAction::Profiles => "Go to Profiles",
+            UiAction::Settings => "Go to Settings",
+            UiAction::NextWidget => "Next item",
+            UiAction::Play => "Play game",
+            UiAction::PrevTab => "Previous tab",
+            UiAction::NextTab => "Next tab",
+            UiAction::AddDevice => "Add device / join instance",
+            UiAction::RemoveDevice => "Remove device / cancel",
+            UiAction::Invite => "Invite to instance",
+            UiAction::Launch => "Start / join session",
+        }
+    }
+}
+
+// Buttons and keys bound to each action by their evdev names, e.g. "BTN_SOUTH" or "KEY_Z"
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct UiBindings(pub BTreeMap<UiAction, Vec<String>>);
+
+impl Default for UiBindings {
+    fn default() -> Self {
+        let bind = |action, codes: &[KeyCode]| {
+            (
+                action,
+                codes.iter().map(|code| format!("{code:?}")).collect(),
+            )
+        };
+        // Flight sticks and wheels have no face buttons, so their first two stand in
+        UiBindings(BTreeMap::from([
+            bind(
+                UiAction::Confirm,
+                &[KeyCode::BTN_SOUTH, KeyCode::BTN_TRIGGER],
+            ),
+            bind(UiAction::Home, &[KeyCode::BTN_EAST, KeyCode::BTN_THUMB]),
+            bind(UiAction::Profiles, &[KeyCode::BTN_NORTH]),
+            bind(UiAction::Settings, &[KeyCode::BTN_WEST]),
+            bind(UiAction::NextWidget, &[KeyCode::BTN_SELECT]),
+            bind(UiAction::Play, &[KeyCode::BTN_START]),
+            bind(UiAction::PrevTab, &[KeyCode::BTN_TL]),
+            bind(UiAction::NextTab, &[KeyCode::BTN_TR]),
+            bind(
+                UiAction::AddDevice,
+                &[
+                    KeyCode::BTN_SOUTH,
+                    KeyCode::BTN_TRIGGER,
+                    KeyCode::KEY_Z,
+                    KeyCode::BTN_RIGHT,
+                ],
+            ),
+            bind(
+                UiAction::RemoveDevice,
+                &[KeyCode::BTN_EAST, KeyCode::BTN_THUMB, KeyCode::KEY_X],
+            ),
+            bind(UiAction::Invite, &[KeyCode::BTN_WEST, KeyCode::KEY_A]),
+            bind(UiAction::Launch, &[KeyCode::BTN_START]),
+        ]))
+    }
+}
+
+impl UiBindings {
+    pub fn codes(&self, action: UiAction) -> Vec<KeyCode> {
+        self.0
+            .get(&action)
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| KeyCode::from_str(name).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // The first of actions that code is bound to
+    pub fn action(&self, code: KeyCode, actions: &[UiAction]) -> Option<UiAction> {
+        actions
+            .iter()
+            .copied()
+            .find(|action| self.codes(*action).contains(&code))
+    }
+
+    pub fn bind(&mut self, action: UiAction, code: KeyCode) {
+        let names = self.0.entry(action).or_default();
+        let name = format!("{code:?}");
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    pub fn unbind(&mut self, action: UiAction, code: KeyCode) {
+        if let Some(names) = self.0.get_mut(&action) {
+            names.retain(|name| *name != format!("{code:?}"));
+        }
+    }
+
+    // What to press for an action, as printed on the controllers connected, e.g. "A / ✖ / Z"
+    pub fn hint(&self, action: UiAction, devices: &[InputDevice]) -> String {
+        let mut vendors: Vec<u16> = Vec::new();
+        for dev in devices {
+            if dev.enabled()
+                && dev.device_type().is_controller()
+                && !vendors.contains(&dev.vendor())
+            {
+                vendors.push(dev.vendor());
+            }
+        }
+        if vendors.is_empty() {
+            vendors.push(0);
+        }
+
+        let mut glyphs: Vec<String> = Vec::new();
+        for code in self.codes(action) {
+            let names: Vec<String> = match is_pad_button(code) {
+                true => vendors.iter().map(|vendor| glyph(code, *vendor)).collect(),
+                false => vec![glyph(code, 0)],
+            };
+            for name in names {
+                if !glyphs.contains(&name) {
+                    glyphs.push(name);
+                }
+            }
+        }
+        glyphs.join(" / ")
+    }
+}
+
+fn is_pad_button(code: KeyCode) -> bool {
+    // BTN_JOYSTICK and BTN_GAMEPAD ranges, the D-pad buttons and the extra ones pads number
+    matches!(code.0, 0x120..=0x13f | 0x220..=0x223 | 0x2c0..=0x2e7)
+}
+
+// The name printed on a button of a device made by vendor, e.g. "✖" for BTN_SOUTH on a
+// PlayStation controller or "Z" for KEY_Z
+pub fn glyph(code: KeyCode, vendor: u16) -> String {
+    // Xbox, PlayStation and Nintendo names, in that order
+    let pick = |names: [&str; 3]| {
+        match vendor {
+            0x054c => names[1],
+            0x057e => names[2],
+            _ => names[0],
+        }
+        .to_string()
+    };
+    match code {
+        KeyCode::BTN_SOUTH => pick(["A", "✖", "B"]),
+        KeyCode::BTN_EAST => pick(["B", "○", "A"]),
+        KeyCode::BTN_NORTH => pick(["Y", "△", "X"]),
+        KeyCode::BTN_WEST => pick(["X", "□", "Y"]),
+        KeyCode::BTN_TL => pick(["LB", "L1", "L"]),
+        KeyCode::BTN_TR => pick(["RB", "R1", "R"]),
+        KeyCode::BTN_TL2 => pick(["LT", "L2", "ZL"]),
+        KeyCode::BTN_TR2 => pick(["RT", "R2", "ZR"]),
+        KeyCode::BTN_SELECT => pick(["Back", "Create", "−"]),
+        KeyCode::BTN_START => pick(["Start", "Options", "+"]),
+        KeyCode::BTN_MODE => pick(["Guide", "PS", "Home"]),
+        KeyCode::BTN_THUMBL => pick(["LS", "L3", "LS"]),
+        KeyCode::BTN_THUMBR => pick(["RS", "R3", "RS"]),
+        KeyCode::BTN_LEFT => "Left Click".to_string(),
+        KeyCode::BTN_RIGHT => "Right Click".to_string(),
+        KeyCode::BTN_MIDDLE => "Middle Click".to_string(),
+        _ => {
+            let name = format!("{code:?}");
+            match name.strip_prefix("KEY_") {
+                Some(key) => key.to_string(),
+                None => name.trim_start_matches("BTN_").to_string(),
+            }
+        }
+    }
+}
+
+// How a bound button or key is listed in the bindings editor, telling gamepad buttons and
+// keyboard keys with the same letter apart
+pub fn binding_label(code: KeyCode) -> String {
+    if let Some((_, label)) = GAMEPAD_BUTTONS.iter().find(|(c, _)| *c == code) {
+        return label.to_string();
+    }
+    let name = format!("{code:?}");
+    match name.starts_with("KEY_") {
+        true => format!("{} key", glyph(code, 0)),
+        false => glyph(code, 0),
+    }
+}
@@ -65,6 +65,13 @@ impl Game {
             Game::HandlerRef(handler) => handler.display(),
         }
     }
+    // Whether the game is a Windows program run through Proton/Wine
+    pub fn is_win(&self) -> bool {
+        match self {
+            Game::ExecRef(e) => e.path().extension().unwrap_or_default() == "exe",
+            Game::HandlerRef(handler) => handler.win,
+        }
+    }
     pub fn icon(&self) -> ImageSource<'_> {
         match self {
             Game::ExecRef(_) => egui::include_image!("../res/executable_icon.png"),
@@ -1,12 +1,50 @@
 use crate::paths::*;
+use crate::sandbox::SandboxPolicy;
 use crate::util::*;
 
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
+use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
 
+// A step run once in each prefix before a Windows game is launched in it
+pub enum SetupStep {
+    Winetricks(String),
+    RegImport(PathBuf),
+    Installer(PathBuf, Vec<String>),
+}
+
+impl SetupStep {
+    // Identifies the step in a prefix's setup state file
+    pub fn key(&self) -> String {
+        match self {
+            SetupStep::Winetricks(verb) => format!("winetricks {verb}"),
+            SetupStep::RegImport(path) => format!("reg {}", path.display()),
+            SetupStep::Installer(path, args) => {
+                format!("installer {} {}", path.display(), args.join(" "))
+            }
+        }
+    }
+}
+
+// An exe in the handler folder run during setup, and the arguments it's run with
+#[derive(Clone)]
+pub struct Installer {
+    pub path: String,
+    pub args: Vec<String>,
+}
+
+#[derive(Clone)]
+pub enum RegValue {
+    String(String),
+    Dword(u32),
+    Delete,
+}
+
 #[derive(Clone)]
 pub struct Handler {
     // Members that are determined by context
@@ -20,6 +58,10 @@ pub struct Handler {
     pub info: String,
 
     pub symlink_dir: bool,
+    pub overlay_dir: bool,
+    pub instance_dirs: bool,
+    pub mirror_instance_dirs: bool,
+    pub sandbox: Option<SandboxPolicy>,
     pub win: bool,
     pub runtime: String,
     pub is32bit: bool,
@@ -27,7 +69,15 @@ pub struct Handler {
     pub args: Vec<String>,
     pub copy_instead_paths: Vec<String>,
     pub remove_paths: Vec<String>,
-    pub dll_overrides: Vec<String>,
+    // DLL name and WINEDLLOVERRIDES mode, e.g. "n,b"; an empty mode disables the DLL
+    pub dll_overrides: Vec<(String, String)>,
+    // Registry key and the values to set under it
+    pub registry: Vec<(String, Vec<(String, RegValue)>)>,
+    pub umu_id: String,
+    pub umu_store: String,
+    pub setup_winetricks: Vec<String>,
+    pub setup_reg: Vec<String>,
+    pub setup_installers: Vec<Installer>,
 
     pub path_goldberg: String,
     pub steam_appid: Option<String>,
@@ -69,6 +119,14 @@ impl Handler {
                 .to_string(),
 
             symlink_dir: json["game.symlink_dir"].as_bool().unwrap_or_default(),
+            overlay_dir: json["game.overlay_dir"].as_bool().unwrap_or_default(),
+            instance_dirs: json["game.instance_dirs"].as_bool().unwrap_or_default(),
+            mirror_instance_dirs: json["game.mirror_instance_dirs"]
+                .as_bool()
+                .unwrap_or_default(),
+            sandbox: json["game.sandbox"]
+                .as_str()
+                .and_then(SandboxPolicy::from_name),
             win: json["game.win"].as_bool().unwrap_or_default(),
             is32bit: json["game.32bit"].as_bool().unwrap_or_default(),
             runtime: json["game.runtime"]
@@ -104,7 +162,14 @@ impl Handler {
                         .collect()
                 })
                 .unwrap_or_default(),
-            dll_overrides: json["game.dll_overrides"]
+            dll_overrides: parse_dll_overrides(&json["game.dll_overrides"])?,
+            registry: parse_registry(&json["game.registry"])?,
+            umu_id: json["game.umu_id"].as_str().unwrap_or_default().to_string(),
+            umu_store: json["game.umu_store"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            setup_winetricks: json["setup.winetricks"]
                 .as_array()
                 .map(|arr| {
                     arr.iter()
@@ -112,6 +177,15 @@ impl Handler {
                         .collect()
                 })
                 .unwrap_or_default(),
+            setup_reg: json["setup.reg"]
+                .as_array()
+                .map(|arr| {
+                    arr.iter()
+                        .map(|v| v.as_str().unwrap_or_default().to_string().sanitize_path())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            setup_installers: parse_installers(&json["setup.installers"])?,
 
             path_goldberg: json["steam.api_path"]
                 .as_str()
@@ -164,6 +238,83 @@ impl Handler {
         }
     }
 
+    // Overlay mode already gives each instance its own writable game folder,
+    // so it takes precedence over the symlink folder and instance folders
+    pub fn uses_instance_dirs(&self) -> bool {
+        self.instance_dirs && !self.overlay_dir
+    }
+
+    // Instance folders are built from the symlink folder
+    pub fn uses_symlink_dir(&self) -> bool {
+        (self.symlink_dir || self.instance_dirs) && !self.overlay_dir
+    }
+
+    // The umu database ID protonfixes are looked up by, falling back to the Steam app ID
+    pub fn umu_gameid(&self) -> Option<String> {
+        if !self.umu_id.is_empty() {
+            return Some(self.umu_id.clone());
+        }
+        self.steam_appid
+            .as_ref()
+            .map(|appid| format!("umu-{appid}"))
+    }
+
+    pub fn umu_store(&self) -> Option<String> {
+        if !self.umu_store.is_empty() {
+            Some(self.umu_store.clone())
+        } else if self.umu_id.is_empty() && self.steam_appid.is_some() {
+            Some("steam".to_string())
+        } else {
+            None
+        }
+    }
+
+    // Setup steps in the order they're run: winetricks verbs, then registry imports, then installers.
+    pub fn setup_steps(&self) -> Vec<SetupStep> {
+        let mut steps = Vec::new();
+        for verb in &self.setup_winetricks {
+            if !verb.is_empty() {
+                steps.push(SetupStep::Winetricks(verb.clone()));
+            }
+        }
+        for reg in &self.setup_reg {
+            if !reg.is_empty() {
+                steps.push(SetupStep::RegImport(self.path_handler.join(reg)));
+            }
+        }
+        for installer in &self.setup_installers {
+            if !installer.path.is_empty() {
+                steps.push(SetupStep::Installer(
+                    self.path_handler.join(&installer.path),
+                    installer.args.clone(),
+                ));
+            }
+        }
+        steps
+    }
+
+    // The handler's registry values as a .reg file, for importing with regedit
+    pub fn registry_file(&self) -> Option<String> {
+        if self.registry.is_empty() {
+            return None;
+        }
+        let mut out = "Windows Registry Editor Version 5.00\n".to_string();
+        for (key, values) in &self.registry {
+            out.push_str(&format!("\n[{key}]\n"));
+            for (name, value) in values {
+                let name = reg_escape(name);
+                match value {
+                    RegValue::String(s) => {
+                        out.push_str(&format!("\"{name}\"=\"{}\"\n", reg_escape(s)))
+                    }
+                    RegValue::Dword(d) => out.push_str(&format!("\"{name}\"=dword:{d:08x}\n")),
+                    RegValue::Delete => out.push_str(&format!("\"{name}\"=-\n")),
+                }
+            }
+        }
+        Some(out)
+    }
+
     fn get_imgs(&self) -> Vec<PathBuf> {
         let mut out = Vec::new();
         let imgs_path = self.path_handler.join("imgs");
@@ -197,6 +348,105 @@ impl Handler {
     }
 }
 
+// Accepts either a list of DLLs, which are all set to "n,b", or an object of DLL names to modes
+fn parse_dll_overrides(value: &Value) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut out = Vec::new();
+    match value {
+        Value::Array(arr) => {
+            for v in arr {
+                out.push((
+                    v.as_str().unwrap_or_default().to_string(),
+                    "n,b".to_string(),
+                ));
+            }
+        }
+        Value::Object(map) => {
+            for (dll, mode) in map {
+                let mode = match mode.as_str().unwrap_or_default() {
+                    "disabled" => "",
+                    m @ ("n" | "b" | "n,b" | "b,n" | "") => m,
+                    m => return Err(format!("Invalid override mode \"{m}\" for {dll}").into()),
+                };
+                out.push((dll.clone(), mode.to_string()));
+            }
+        }
+        _ => {}
+    }
+    out.retain(|(dll, _)| !dll.is_empty());
+    Ok(out)
+}
+
+// An object of registry keys, each an object of value names to a string, a number (DWORD),
+// or null to delete the value
+fn parse_registry(value: &Value) -> Result<Vec<(String, Vec<(String, RegValue)>)>, Box<dyn Error>> {
+    let mut out = Vec::new();
+    let Some(keys) = value.as_object() else {
+        return Ok(out);
+    };
+    for (key, values) in keys {
+        let values = values
+            .as_object()
+            .ok_or_else(|| format!("Registry key {key} must be an object of values"))?;
+        let mut parsed = Vec::new();
+        for (name, v) in values {
+            let v = match v {
+                Value::String(s) => RegValue::String(s.clone()),
+                Value::Number(n) => RegValue::Dword(
+                    n.as_u64()
+                        .and_then(|n| u32::try_from(n).ok())
+                        .ok_or_else(|| format!("Registry value {name} is not a valid DWORD"))?,
+                ),
+                Value::Null => RegValue::Delete,
+                _ => return Err(format!("Unsupported type for registry value {name}").into()),
+            };
+            parsed.push((name.clone(), v));
+        }
+        out.push((key.clone(), parsed));
+    }
+    Ok(out)
+}
+
+// Installer entries are either the exe's path, or an object with its "path" and an "args" array
+fn parse_installers(value: &Value) -> Result<Vec<Installer>, Box<dyn Error>> {
+    let mut out = Vec::new();
+    let Some(entries) = value.as_array() else {
+        return Ok(out);
+    };
+    for entry in entries {
+        let (path, args) = match entry {
+            Value::String(path) => (path.clone(), Vec::new()),
+            Value::Object(obj) => {
+                let path = obj
+                    .get("path")
+                    .and_then(|path| path.as_str())
+                    .ok_or_else(|| "Installer entries need a \"path\"".to_string())?;
+                let args = match obj.get("args") {
+                    None => Vec::new(),
+                    Some(args) => args
+                        .as_array()
+                        .and_then(|arr| {
+                            arr.iter()
+                                .map(|arg| arg.as_str().map(|s| s.to_string()))
+                                .collect()
+                        })
+                        .ok_or_else(|| format!("Arguments for installer {path} must be strings"))?,
+                };
+                (path.to_string(), args)
+            }
+            _ => return Err("Installer entries must be a path or an object".into()),
+        };
+        out.push(Installer {
+            path: path.sanitize_path(),
+            args,
+        });
+    }
+    Ok(out)
+}
+
+fn reg_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 pub fn scan_handlers() -> Vec<Handler> {
     let mut out: Vec<Handler> = Vec::new();
     let handlers_path = PATH_PARTY.join("handlers");
@@ -267,34 +517,120 @@ pub fn install_handler_from_file(file: &PathBuf) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+// One file, directory or symlink in a game's folder, as recorded when its symlink folder was built
+#[derive(Serialize, Deserialize, PartialEq)]
+struct ManifestEntry {
+    dir: bool,
+    size: u64,
+    mtime: i64,
+}
+
+// Records what a symlink folder was built from, so changes to the game can be detected
+#[derive(Serialize, Deserialize)]
+struct SymlinkManifest {
+    handler_version: String,
+    root: PathBuf,
+    entries: HashMap<PathBuf, ManifestEntry>,
+}
+
+impl SymlinkManifest {
+    fn scan(h: &Handler, path_root: &PathBuf) -> Result<Self, Box<dyn Error>> {
+        let mut entries = HashMap::new();
+        for entry in walkdir::WalkDir::new(path_root)
+            .min_depth(1)
+            .follow_links(false)
+        {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            entries.insert(
+                entry.path().strip_prefix(path_root)?.to_path_buf(),
+                ManifestEntry {
+                    dir: meta.is_dir(),
+                    size: meta.len(),
+                    mtime: meta.mtime(),
+                },
+            );
+        }
+        Ok(SymlinkManifest {
+            handler_version: h.version.clone(),
+            root: path_root.clone(),
+            entries,
+        })
+    }
+}
+
+fn manifest_path(h: &Handler) -> PathBuf {
+    PATH_PARTY.join(format!("gamesyms/.{}.json", h.uid))
+}
+
+// The shared symlink folder sits beside the instances' game folders in gamesyms/<uid>
+pub fn symlink_gamedir(h: &Handler) -> PathBuf {
+    PATH_PARTY.join(format!("gamesyms/{}/shared", h.uid))
+}
+
+// Builds the handler's symlink folder, or brings an existing one up to date with the game's
+// files. A new handler version or game location rebuilds it from scratch.
 pub fn create_symlink_folder(h: &Handler) -> Result<(), Box<dyn Error>> {
     let path_root = PathBuf::from(get_rootpath_handler(&h)?);
-    let path_sym = PATH_PARTY.join(format!("gamesyms/{}", h.uid));
-    if path_sym.exists() {
-        return Ok(());
+    let path_sym = symlink_gamedir(h);
+
+    let manifest = SymlinkManifest::scan(h, &path_root)?;
+    let old_manifest: Option<SymlinkManifest> = File::open(manifest_path(h))
+        .ok()
+        .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok());
+
+    match old_manifest {
+        Some(old)
+            if path_sym.exists()
+                && old.handler_version == h.version
+                && old.root == manifest.root =>
+        {
+            if !update_symlink_folder(h, &path_root, &path_sym, &old, &manifest)? {
+                return Ok(());
+            }
+        }
+        Some(_) => {
+            if path_sym.exists() {
+                println!("Rebuilding symlink folder for {}", h.uid);
+                std::fs::remove_dir_all(&path_sym)?;
+            }
+            build_symlink_folder(h, &path_root, &path_sym)?;
+        }
+        None => {
+            // Older versions built the symlink folder right in gamesyms/<uid>
+            let path_old = PATH_PARTY.join(format!("gamesyms/{}", h.uid));
+            if path_old.exists() && !path_sym.exists() {
+                std::fs::remove_dir_all(&path_old)?;
+            }
+            build_symlink_folder(h, &path_root, &path_sym)?;
+        }
     }
-    std::fs::create_dir_all(path_sym.to_owned())?;
-    copy_dir_recursive(&path_root, &path_sym, true, false)?;
+
+    apply_symlink_overlays(h, &path_root, &path_sym)?;
+    std::fs::write(manifest_path(h), serde_json::to_string(&manifest)?)?;
+    Ok(())
+}
+
+fn build_symlink_folder(
+    h: &Handler,
+    path_root: &PathBuf,
+    path_sym: &PathBuf,
+) -> Result<(), Box<dyn Error>> {
+    std::fs::create_dir_all(path_sym)?;
+    copy_dir_recursive(path_root, path_sym, true, false)?;
 
     // copy_instead_paths takes symlink files and replaces them with their real equivalents
+    let mut copies = Vec::new();
     for path in &h.copy_instead_paths {
         let src = path_root.join(path);
         if !src.exists() {
             continue;
         }
-        let dest = path_sym.join(path);
-        println!("src: {}, dest: {}", src.display(), dest.display());
-        if src.is_dir() {
-            println!("Copying directory: {}", src.display());
-            copy_dir_recursive(&src, &dest, false, true)?;
-        } else if src.is_file() {
-            println!("Copying file: {}", src.display());
-            if dest.exists() {
-                std::fs::remove_file(&dest)?;
-            }
-            std::fs::copy(&src, &dest)?;
-        }
+        println!("Copying: {}", src.display());
+        copies.push((src, path_sym.join(path)));
     }
+    copy_paths(&copies)?;
+
     for path in h.remove_paths.iter().chain(h.game_unique_paths.iter()) {
         let p = path_sym.join(path);
         if !p.exists() {
@@ -306,6 +642,231 @@ pub fn create_symlink_folder(h: &Handler) -> Result<(), Box<dyn Error>> {
             std::fs::remove_file(p)?;
         }
     }
+    Ok(())
+}
+
+// Links, copies or removes only the game files that changed since the manifest was written.
+// Returns whether anything changed.
+fn update_symlink_folder(
+    h: &Handler,
+    path_root: &PathBuf,
+    path_sym: &PathBuf,
+    old: &SymlinkManifest,
+    new: &SymlinkManifest,
+) -> Result<bool, Box<dyn Error>> {
+    let removed: Vec<&PathBuf> = old
+        .entries
+        .keys()
+        .filter(|path| !new.entries.contains_key(*path))
+        .collect();
+    let mut changed: Vec<&PathBuf> = new
+        .entries
+        .iter()
+        .filter(|(path, entry)| old.entries.get(*path) != Some(entry))
+        .map(|(path, _)| path)
+        .collect();
+    if removed.is_empty() && changed.is_empty() {
+        return Ok(false);
+    }
+    println!(
+        "Game files for {} changed ({} changed, {} removed), updating symlink folder",
+        h.uid,
+        changed.len(),
+        removed.len()
+    );
+
+    let under = |path: &PathBuf, list: &Vec<String>| {
+        list.iter().any(|p| !p.is_empty() && path.starts_with(p))
+    };
+
+    for path in removed {
+        let dest = path_sym.join(path);
+        if dest.is_symlink() || dest.is_file() {
+            std::fs::remove_file(&dest)?;
+        } else if dest.is_dir() {
+            std::fs::remove_dir_all(&dest)?;
+        }
+    }
+
+    // Parents sort before their children, so directories exist before their contents are linked
+    changed.sort();
+    let mut copies = Vec::new();
+    for path in changed {
+        if under(path, &h.remove_paths) || under(path, &h.game_unique_paths) {
+            continue;
+        }
+        let src = path_root.join(path);
+        let dest = path_sym.join(path);
+        let src_dir = src.is_dir() && !src.is_symlink();
+        let dest_dir = dest.is_dir() && !dest.is_symlink();
+        // An entry that switched between file and folder replaces what was there
+        if dest_dir && !src_dir {
+            std::fs::remove_dir_all(&dest)?;
+        } else if !dest_dir && dest.symlink_metadata().is_ok() {
+            std::fs::remove_file(&dest)?;
+        }
+        if src_dir {
+            std::fs::create_dir_all(&dest)?;
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if src.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(&src)?, &dest)?;
+        } else if under(path, &h.copy_instead_paths) {
+            copies.push((src, dest));
+        } else {
+            std::os::unix::fs::symlink(&src, &dest)?;
+        }
+    }
+    copy_paths(&copies)?;
+    Ok(true)
+}
+
+// Files up to this size are copied into instance folders instead of linked, since a game
+// writing to a linked config or save file would write through to the original
+const INSTANCE_DIR_COPY_SIZE: u64 = 1024 * 1024;
+
+// Per-instance game folder, built beside the shared symlink folder
+pub fn instance_gamedir(h: &Handler, i: usize) -> PathBuf {
+    PATH_PARTY.join(format!("gamesyms/{}/{}", h.uid, i + 1))
+}
+
+fn instance_dir_marker(h: &Handler, i: usize) -> PathBuf {
+    PATH_PARTY.join(format!("gamesyms/{}/.{}.synced", h.uid, i + 1))
+}
+
+// Where files an instance wrote into its game folder are mirrored in the player's profile
+fn mirrored_gamedir(h: &Handler, profname: &str) -> PathBuf {
+    PATH_PARTY
+        .join("profiles")
+        .join(profname)
+        .join("saves")
+        .join(&h.uid)
+        .join("_gamedir")
+}
+
+// Brings an instance's game folder up to date with the shared symlink folder, keeping files the
+// game wrote there, then restores the files mirrored into the player's profile
+pub fn create_instance_dir(h: &Handler, i: usize, profname: &str) -> Result<(), Box<dyn Error>> {
+    let path_sym = symlink_gamedir(h);
+    let path_inst = instance_gamedir(h, i);
+    std::fs::create_dir_all(&path_inst)?;
+    // Copies the game has written to since the last session are kept over newer game files
+    let synced = std::fs::metadata(instance_dir_marker(h, i))
+        .and_then(|meta| meta.modified())
+        .ok();
+
+    for entry in walkdir::WalkDir::new(&path_sym)
+        .min_depth(1)
+        .follow_links(false)
+    {
+        let entry = entry?;
+        let dest = path_inst.join(entry.path().strip_prefix(&path_sym)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&dest)?;
+            continue;
+        }
+        // Follow the farm's link so small files can be copied from the real game folder
+        let Ok(src) = std::fs::canonicalize(entry.path()) else {
+            continue;
+        };
+        let src_meta = src.metadata()?;
+        if dest.is_symlink() {
+            continue;
+        }
+        if let Ok(dest_meta) = dest.metadata() {
+            let written =
+                synced.is_none_or(|synced| dest_meta.modified().is_ok_and(|m| m > synced));
+            let unchanged = dest_meta.len() == src_meta.len()
+                && dest_meta.modified().ok() == src_meta.modified().ok();
+            if written || unchanged {
+                continue;
+            }
+        }
+        if entry.file_type().is_symlink() && src_meta.len() > INSTANCE_DIR_COPY_SIZE {
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &dest)?;
+        } else {
+            std::fs::copy(&src, &dest)?;
+            // Copies keep the game file's mtime, so an updated game file can be told apart
+            File::options()
+                .write(true)
+                .open(&dest)?
+                .set_modified(src_meta.modified()?)?;
+        }
+    }
+
+    // Drop links to files the game no longer has
+    for entry in walkdir::WalkDir::new(&path_inst)
+        .min_depth(1)
+        .follow_links(false)
+    {
+        let entry = entry?;
+        if entry.file_type().is_symlink() && !entry.path().exists() {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+
+    let mirrored = mirrored_gamedir(h, profname);
+    if h.mirror_instance_dirs && mirrored.exists() {
+        copy_dir_recursive(&mirrored, &path_inst, false, true)?;
+    }
+
+    // Anything modified after this marker was written by the game
+    std::fs::write(instance_dir_marker(h, i), "")?;
+    Ok(())
+}
+
+// Copies the files the game wrote into an instance's game folder this session to the player's profile
+pub fn mirror_instance_dir(h: &Handler, i: usize, profname: &str) -> Result<(), Box<dyn Error>> {
+    let path_inst = instance_gamedir(h, i);
+    let since = std::fs::metadata(instance_dir_marker(h, i))?.modified()?;
+    let mirrored = mirrored_gamedir(h, profname);
+
+    for entry in walkdir::WalkDir::new(&path_inst)
+        .min_depth(1)
+        .follow_links(false)
+    {
+        let entry = entry?;
+        if !entry.file_type().is_file() || entry.metadata()?.modified()? <= since {
+            continue;
+        }
+        let dest = mirrored.join(entry.path().strip_prefix(&path_inst)?);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        println!("Mirroring {} to {}", entry.path().display(), dest.display());
+        std::fs::copy(entry.path(), dest)?;
+    }
+    Ok(())
+}
+
+// In overlay mode, the handler's files and Goldberg go in a layer mounted between the game's
+// files and each instance's writable layer. remove_paths and copy_instead_paths don't apply.
+pub fn create_overlay_layer(h: &Handler) -> Result<(), Box<dyn Error>> {
+    let path_root = PathBuf::from(get_rootpath_handler(&h)?);
+    let path_layer = PATH_PARTY.join(format!("gamelayers/{}", h.uid));
+    let marker = PATH_PARTY.join(format!("gamelayers/.{}", h.uid));
+    if path_layer.exists() && std::fs::read_to_string(&marker).is_ok_and(|v| v == h.version) {
+        return Ok(());
+    }
+
+    if path_layer.exists() {
+        std::fs::remove_dir_all(&path_layer)?;
+    }
+    std::fs::create_dir_all(&path_layer)?;
+    apply_symlink_overlays(h, &path_root, &path_layer)?;
+    std::fs::write(marker, &h.version)?;
+    Ok(())
+}
+
+// Handler files and Goldberg go on top of the game's files, so they're reapplied after every update
+fn apply_symlink_overlays(
+    h: &Handler,
+    path_root: &PathBuf,
+    path_sym: &PathBuf,
+) -> Result<(), Box<dyn Error>> {
     let copypath = PathBuf::from(&h.path_handler).join("copy_to_symdir");
     if copypath.exists() {
         copy_dir_recursive(&copypath, &path_sym, false, true)?;
@@ -1,41 +1,159 @@
 use crate::app::PadFilterType;
+use crate::instance::Instance;
+use crate::vpad::VPAD_PHYS;
 
 use evdev::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-#[derive(Clone, PartialEq, Copy)]
+// Set by the hotplug watcher when /dev/input changes
+static DEVICES_CHANGED: AtomicBool = AtomicBool::new(false);
+static WATCH_STARTED: Once = Once::new();
+// How long the watcher waits for a burst of changes to settle before reporting them
+const HOTPLUG_SETTLE: Duration = Duration::from_millis(300);
+// How far the left stick has to be pushed to navigate, as a fraction of its range
+const STICK_DEADZONE: f32 = 0.5;
+// A held direction repeats after REPEAT_DELAY, then every REPEAT_INTERVAL
+const REPEAT_DELAY: Duration = Duration::from_millis(400);
+const REPEAT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, PartialEq, Copy, Serialize, Deserialize)]
 pub enum DeviceType {
     Gamepad,
+    Joystick,
+    Wheel,
     Keyboard,
     Mouse,
+    Touchpad,
+    // Only ever part of another device, e.g. a controller's gyro or a keyboard's volume keys
+    MotionSensor,
+    MediaKeys,
     Other,
 }
 
+impl DeviceType {
+    // Devices a player plays with directly, as opposed to keyboards and mice
+    pub fn is_controller(&self) -> bool {
+        matches!(
+            self,
+            DeviceType::Gamepad | DeviceType::Joystick | DeviceType::Wheel
+        )
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, DeviceType::Mouse | DeviceType::Touchpad)
+    }
+
+    // Which of a physical device's nodes is listed for it when it has several
+    fn priority(&self) -> u8 {
+        match self {
+            DeviceType::Gamepad => 0,
+            DeviceType::Wheel => 1,
+            DeviceType::Joystick => 2,
+            DeviceType::Keyboard => 3,
+            DeviceType::Mouse => 4,
+            DeviceType::Touchpad => 5,
+            DeviceType::MotionSensor => 6,
+            DeviceType::MediaKeys => 7,
+            DeviceType::Other => 8,
+        }
+    }
+
+    // Whether a node of this type is folded into a listed device of the same physical device,
+    // rather than being listed itself
+    fn joins(&self, listed: DeviceType) -> bool {
+        *self == listed
+            || matches!(self, DeviceType::MotionSensor | DeviceType::MediaKeys)
+            || (*self == DeviceType::Touchpad && listed.is_controller())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
 pub enum PadButton {
     Left,
     Right,
     Up,
     Down,
-    ABtn,
-    BBtn,
-    XBtn,
-    YBtn,
-    StartBtn,
-    SelectBtn,
+    // Any other button or key; what it does is up to the UI bindings
+    Press(KeyCode),
+}
+
+// Identifies a physical device across reconnects and re-enumeration, unlike its event path.
+// Wireless devices report their address as uniq; wired ones are told apart by the port they're in.
+#[derive(Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct DeviceId {
+    pub vendor: u16,
+    pub product: u16,
+    pub uniq: String,
+    pub phys: String,
+}
+
+impl DeviceId {
+    pub fn of(dev: &Device) -> Self {
+        DeviceId {
+            vendor: dev.input_id().vendor(),
+            product: dev.input_id().product(),
+            uniq: dev.unique_name().unwrap_or_default().to_string(),
+            phys: dev.physical_path().unwrap_or_default().to_string(),
+        }
+    }
 
-    AKey,
-    RKey,
-    XKey,
-    ZKey,
+    pub fn matches(&self, other: &DeviceId) -> bool {
+        self.vendor == other.vendor
+            && self.product == other.product
+            && match self.uniq.is_empty() {
+                false => self.uniq == other.uniq,
+                true => self.phys == other.phys,
+            }
+    }
 
-    RightClick,
+    // What the device is remembered by, e.g. "057e:2009:98:b6:e9:01:02:03"
+    pub fn key(&self) -> String {
+        let place = match self.uniq.is_empty() {
+            false => &self.uniq,
+            true => &self.phys,
+        };
+        format!("{:04x}:{:04x}:{place}", self.vendor, self.product)
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DeviceInfo {
     pub path: String,
     pub vendor: u16,
+    #[serde(default)]
+    pub id: DeviceId,
     pub enabled: bool,
     pub device_type: DeviceType,
+    // The physical device's other event nodes, e.g. a controller's motion sensors
+    #[serde(default)]
+    pub siblings: Vec<String>,
+    // Node of the virtual pad standing in for this device during a session
+    #[serde(default)]
+    pub virtual_path: Option<String>,
+}
+
+impl DeviceInfo {
+    // Identifies the controller model, e.g. "057e:2009"
+    pub fn model_key(&self) -> String {
+        format!("{:04x}:{:04x}", self.id.vendor, self.id.product)
+    }
+}
+
+// Index of the device that's the same physical device as id. Devices are matched by identity since
+// a new device can reuse a freed path; the path only decides between nodes sharing an identity.
+pub fn find_device(devices: &[DeviceInfo], id: &DeviceId, path: &str) -> Option<usize> {
+    let candidates: Vec<usize> = (0..devices.len())
+        .filter(|n| devices[*n].id == *id)
+        .collect();
+    candidates
+        .iter()
+        .copied()
+        .find(|n| devices[*n].path == path)
+        .or(candidates.first().copied())
 }
 
 pub struct InputDevice {
@@ -43,7 +161,128 @@ pub struct InputDevice {
     dev: Device,
     enabled: bool,
     device_type: DeviceType,
+    siblings: Vec<(String, Device)>,
     has_button_held: bool,
+    nav: NavState,
+}
+
+// What GUI navigation needs to remember between polls
+#[derive(Default)]
+struct NavState {
+    // Presses not yet handed out by poll
+    pending: VecDeque<PadButton>,
+    hat: (i32, i32),
+    // D-pad buttons held: up, down, left, right
+    dpad: [bool; 4],
+    // Left stick position from -1 to 1, and each axis' range; None on devices without one
+    stick: (f32, f32),
+    stick_range: Option<((i32, i32), (i32, i32))>,
+    // The direction held and when it next repeats
+    held: Option<(PadButton, Instant)>,
+}
+
+impl NavState {
+    fn new(dev: &Device, device_type: DeviceType) -> Self {
+        // Wheels steer with ABS_X, which would make every turn scroll the GUI
+        let stick_range = match device_type {
+            DeviceType::Gamepad | DeviceType::Joystick => dev.get_absinfo().ok().and_then(|axes| {
+                let mut x = None;
+                let mut y = None;
+                for (code, info) in axes {
+                    match code {
+                        AbsoluteAxisCode::ABS_X => x = Some((info.minimum(), info.maximum())),
+                        AbsoluteAxisCode::ABS_Y => y = Some((info.minimum(), info.maximum())),
+                        _ => {}
+                    }
+                }
+                Some((x?, y?))
+            }),
+            _ => None,
+        };
+        NavState {
+            stick_range,
+            ..Default::default()
+        }
+    }
+
+    fn handle(&mut self, summary: EventSummary) {
+        match summary {
+            EventSummary::Key(_, code, value) if value != 2 => {
+                let pressed = value == 1;
+                match code {
+                    KeyCode::BTN_DPAD_UP => self.dpad[0] = pressed,
+                    KeyCode::BTN_DPAD_DOWN => self.dpad[1] = pressed,
+                    KeyCode::BTN_DPAD_LEFT => self.dpad[2] = pressed,
+                    KeyCode::BTN_DPAD_RIGHT => self.dpad[3] = pressed,
+                    _ if pressed => self.pending.push_back(PadButton::Press(code)),
+                    _ => {}
+                }
+            }
+            EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_HAT0X, value) => self.hat.0 = value,
+            EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_HAT0Y, value) => self.hat.1 = value,
+            EventSummary::AbsoluteAxis(_, code, value) => {
+                let Some((x_range, y_range)) = self.stick_range else {
+                    return;
+                };
+                match code {
+                    AbsoluteAxisCode::ABS_X => self.stick.0 = normalize(value, x_range),
+                    AbsoluteAxisCode::ABS_Y => self.stick.1 = normalize(value, y_range),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The D-pad wins over the stick, and the stick goes the way it's pushed furthest
+    fn direction(&self) -> Option<PadButton> {
+        let (x, y) = match (self.hat, self.dpad) {
+            ((0, 0), [false, false, false, false]) => {
+                let (x, y) = self.stick;
+                match x.abs() > y.abs() {
+                    true if x.abs() > STICK_DEADZONE => (x.signum() as i32, 0),
+                    false if y.abs() > STICK_DEADZONE => (0, y.signum() as i32),
+                    _ => (0, 0),
+                }
+            }
+            ((0, 0), [up, down, left, right]) => {
+                (right as i32 - left as i32, down as i32 - up as i32)
+            }
+            (hat, _) => hat,
+        };
+        match (x, y) {
+            (_, -1) => Some(PadButton::Up),
+            (_, 1) => Some(PadButton::Down),
+            (-1, _) => Some(PadButton::Left),
+            (1, _) => Some(PadButton::Right),
+            _ => None,
+        }
+    }
+
+    // Queues a held direction when it's first pressed and each time it repeats
+    fn update_held(&mut self) {
+        let now = Instant::now();
+        match (self.direction(), self.held) {
+            (Some(dir), Some((held, next))) if dir == held => {
+                if now >= next {
+                    self.pending.push_back(dir);
+                    self.held = Some((dir, now + REPEAT_INTERVAL));
+                }
+            }
+            (Some(dir), _) => {
+                self.pending.push_back(dir);
+                self.held = Some((dir, now + REPEAT_DELAY));
+            }
+            (None, _) => self.held = None,
+        }
+    }
+}
+
+fn normalize(value: i32, (min, max): (i32, i32)) -> f32 {
+    if max == min {
+        return 0.0;
+    }
+    (value - min) as f32 / (max - min) as f32 * 2.0 - 1.0
 }
 impl InputDevice {
     pub fn name(&self) -> &str {
@@ -52,9 +291,12 @@ impl InputDevice {
     pub fn emoji(&self) -> &str {
         match self.device_type() {
             DeviceType::Gamepad => "🎮",
-            DeviceType::Keyboard => "🖮",
+            DeviceType::Joystick => "🕹",
+            DeviceType::Wheel => "🚗",
+            DeviceType::Keyboard | DeviceType::MediaKeys => "🖮",
             DeviceType::Mouse => "🖱",
-            DeviceType::Other => "",
+            DeviceType::Touchpad => "👆",
+            DeviceType::MotionSensor | DeviceType::Other => "",
         }
     }
     pub fn fancyname(&self) -> &str {
@@ -72,6 +314,9 @@ impl InputDevice {
     pub fn vendor(&self) -> u16 {
         self.dev.input_id().vendor()
     }
+    pub fn id(&self) -> DeviceId {
+        DeviceId::of(&self.dev)
+    }
     pub fn enabled(&self) -> bool {
         self.enabled
     }
@@ -85,105 +330,300 @@ impl InputDevice {
         DeviceInfo {
             path: self.path().to_string(),
             vendor: self.vendor(),
+            id: self.id(),
             enabled: self.enabled(),
             device_type: self.device_type(),
+            siblings: self.siblings.iter().map(|(path, _)| path.clone()).collect(),
+            virtual_path: None,
         }
     }
+    // Returns the next button pressed since the last poll, in order. Held directions repeat
+    // as long as the device is polled.
     pub fn poll(&mut self) -> Option<PadButton> {
-        let mut btn: Option<PadButton> = None;
-        if let Ok(events) = self.dev.fetch_events() {
-            for event in events {
-                let summary = event.destructure();
-
-                match summary {
-                    EventSummary::Key(_, _, 1) => {
-                        self.has_button_held = true;
-                    }
-                    EventSummary::Key(_, _, 0) => {
-                        self.has_button_held = false;
-                    }
-                    _ => {}
-                }
+        self.read_events();
+        self.nav.update_held();
+        self.nav.pending.pop_front()
+    }
 
-                btn = match summary {
-                    EventSummary::Key(_, KeyCode::BTN_SOUTH, 1) => Some(PadButton::ABtn),
-                    EventSummary::Key(_, KeyCode::BTN_EAST, 1) => Some(PadButton::BBtn),
-                    EventSummary::Key(_, KeyCode::BTN_NORTH, 1) => Some(PadButton::XBtn),
-                    EventSummary::Key(_, KeyCode::BTN_WEST, 1) => Some(PadButton::YBtn),
-                    EventSummary::Key(_, KeyCode::BTN_START, 1) => Some(PadButton::StartBtn),
-                    EventSummary::Key(_, KeyCode::BTN_SELECT, 1) => Some(PadButton::SelectBtn),
-                    EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_HAT0X, -1) => {
-                        Some(PadButton::Left)
-                    }
-                    EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_HAT0X, 1) => {
-                        Some(PadButton::Right)
-                    }
-                    EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_HAT0Y, -1) => {
-                        Some(PadButton::Up)
-                    }
-                    EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_HAT0Y, 1) => {
-                        Some(PadButton::Down)
-                    }
-                    //keyboard
-                    EventSummary::Key(_, KeyCode::KEY_A, 1) => Some(PadButton::AKey),
-                    EventSummary::Key(_, KeyCode::KEY_R, 1) => Some(PadButton::RKey),
-                    EventSummary::Key(_, KeyCode::KEY_X, 1) => Some(PadButton::XKey),
-                    EventSummary::Key(_, KeyCode::KEY_Z, 1) => Some(PadButton::ZKey),
-                    //mouse
-                    EventSummary::Key(_, KeyCode::BTN_RIGHT, 1) => Some(PadButton::RightClick),
-                    _ => btn,
-                };
+    // Reads queued input without acting on it, so presses made while the launcher wasn't in
+    // use aren't replayed later. Held buttons and directions are still tracked.
+    pub fn discard_events(&mut self) {
+        self.read_events();
+        self.nav.update_held();
+        self.nav.pending.clear();
+    }
+
+    // Only the device's own node is read. Its siblings are motion sensors, touchpads and media
+    // keys, whose axes would move the stick and whose touches and clicks would count as presses.
+    fn read_events(&mut self) {
+        let events: Vec<InputEvent> = match self.dev.fetch_events() {
+            Ok(fetched) => fetched.collect(),
+            Err(_) => return,
+        };
+        for event in events {
+            let summary = event.destructure();
+
+            match summary {
+                EventSummary::Key(_, _, 1) => {
+                    self.has_button_held = true;
+                }
+                EventSummary::Key(_, _, 0) => {
+                    self.has_button_held = false;
+                }
+                _ => {}
             }
+            self.nav.handle(summary);
         }
-        btn
     }
 }
 
 pub fn scan_input_devices(filter: &PadFilterType) -> Vec<InputDevice> {
-    let mut pads: Vec<InputDevice> = Vec::new();
+    let mut nodes: Vec<(String, Device, DeviceType)> = Vec::new();
     for dev in evdev::enumerate() {
+        // Skip PartyDeck's own virtual pads
+        if dev.1.physical_path() == Some(VPAD_PHYS) {
+            continue;
+        }
+        let device_type = classify(&dev.1);
+        if device_type == DeviceType::Other {
+            continue;
+        }
+        if dev.1.set_nonblocking(true).is_err() {
+            println!("Failed to set non-blocking mode for {}", dev.0.display());
+            continue;
+        }
+        nodes.push((dev.0.to_str().unwrap().to_string(), dev.1, device_type));
+    }
+    nodes.sort_by(|a, b| (a.2.priority(), &a.0).cmp(&(b.2.priority(), &b.0)));
+
+    // Nodes of one physical device are listed once, under its most important node. A keyboard
+    // with a built-in touchpad still lists both, as they can go to different players.
+    let mut pads: Vec<InputDevice> = Vec::new();
+    let mut roots: Vec<Option<std::path::PathBuf>> = Vec::new();
+    for (path, dev, device_type) in nodes {
+        let root = std::path::Path::new(&path)
+            .file_name()
+            .and_then(|name| physical_root(&std::path::Path::new("/sys/class/input").join(name)));
+        let listed = pads.iter().zip(&roots).position(|(pad, pad_root)| {
+            root.is_some() && *pad_root == root && device_type.joins(pad.device_type)
+        });
+        if let Some(i) = listed {
+            pads[i].siblings.push((path, dev));
+            continue;
+        }
+        if matches!(
+            device_type,
+            DeviceType::MotionSensor | DeviceType::MediaKeys
+        ) {
+            continue;
+        }
         let enabled = match filter {
             PadFilterType::All => true,
-            PadFilterType::NoSteamInput => dev.1.input_id().vendor() != 0x28de,
-            PadFilterType::OnlySteamInput => dev.1.input_id().vendor() == 0x28de,
+            PadFilterType::NoSteamInput => dev.input_id().vendor() != 0x28de,
+            PadFilterType::OnlySteamInput => dev.input_id().vendor() == 0x28de,
         };
+        let nav = NavState::new(&dev, device_type);
+        pads.push(InputDevice {
+            path,
+            dev,
+            enabled,
+            device_type,
+            siblings: Vec::new(),
+            has_button_held: false,
+            nav,
+        });
+        roots.push(root);
+    }
+    pads.sort_by_key(|pad| pad.path().to_string());
+    pads
+}
 
-        let device_type = if dev
-            .1
-            .supported_keys()
-            .map_or(false, |keys| keys.contains(KeyCode::BTN_SOUTH))
-        {
-            DeviceType::Gamepad
-        } else if dev
-            .1
-            .supported_keys()
-            .map_or(false, |keys| keys.contains(KeyCode::BTN_LEFT))
-        {
-            DeviceType::Mouse
-        } else if dev
-            .1
-            .supported_keys()
-            .map_or(false, |keys| keys.contains(KeyCode::KEY_SPACE))
-        {
-            DeviceType::Keyboard
-        } else {
-            DeviceType::Other
-        };
+fn classify(dev: &Device) -> DeviceType {
+    let has_key = |key| dev.supported_keys().is_some_and(|keys| keys.contains(key));
+    let has_abs = |axis| {
+        dev.supported_absolute_axes()
+            .is_some_and(|axes| axes.contains(axis))
+    };
+
+    if dev.properties().contains(PropType::ACCELEROMETER) {
+        DeviceType::MotionSensor
+    } else if has_key(KeyCode::BTN_SOUTH) {
+        DeviceType::Gamepad
+    } else if has_abs(AbsoluteAxisCode::ABS_WHEEL)
+        || (has_abs(AbsoluteAxisCode::ABS_X)
+            && (has_abs(AbsoluteAxisCode::ABS_GAS) || has_abs(AbsoluteAxisCode::ABS_BRAKE)))
+    {
+        DeviceType::Wheel
+    } else if has_abs(AbsoluteAxisCode::ABS_X)
+        && (has_key(KeyCode::BTN_TRIGGER) || has_key(KeyCode::BTN_THUMB))
+    {
+        DeviceType::Joystick
+    } else if has_key(KeyCode::BTN_TOOL_FINGER) && has_abs(AbsoluteAxisCode::ABS_X) {
+        DeviceType::Touchpad
+    } else if has_key(KeyCode::BTN_LEFT) {
+        DeviceType::Mouse
+    } else if has_key(KeyCode::KEY_SPACE) {
+        DeviceType::Keyboard
+    } else if [
+        KeyCode::KEY_VOLUMEUP,
+        KeyCode::KEY_MUTE,
+        KeyCode::KEY_PLAYPAUSE,
+        KeyCode::KEY_NEXTSONG,
+    ]
+    .into_iter()
+    .any(has_key)
+    {
+        DeviceType::MediaKeys
+    } else {
+        DeviceType::Other
+    }
+}
 
-        if device_type != DeviceType::Other {
-            if dev.1.set_nonblocking(true).is_err() {
-                println!("Failed to set non-blocking mode for {}", dev.0.display());
+// Watches /dev/input for event nodes being added or removed. udev changing a new node's
+// permissions counts too, as that's when PartyDeck can open it.
+pub fn watch_input_devices() {
+    WATCH_STARTED.call_once(|| {
+        std::thread::spawn(|| {
+            if let Err(err) = watch_dev_input() {
+                println!("[PARTYDECK] Couldn't watch for input devices: {err}");
+            }
+        });
+    });
+}
+
+fn watch_dev_input() -> std::io::Result<()> {
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mask = libc::IN_CREATE | libc::IN_DELETE | libc::IN_ATTRIB;
+    if unsafe { libc::inotify_add_watch(fd, c"/dev/input".as_ptr(), mask) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // Only event nodes matter; names follow each inotify_event header
+        let mut changed = false;
+        let mut offset = 0;
+        while offset + std::mem::size_of::<libc::inotify_event>() <= n as usize {
+            let event = unsafe { &*(buf.as_ptr().add(offset) as *const libc::inotify_event) };
+            let name_start = offset + std::mem::size_of::<libc::inotify_event>();
+            let name = &buf[name_start..name_start + event.len as usize];
+            changed |= name.starts_with(b"event");
+            offset = name_start + event.len as usize;
+        }
+        if changed {
+            std::thread::sleep(HOTPLUG_SETTLE);
+            DEVICES_CHANGED.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// Whether devices were added or removed since this was last called
+pub fn input_devices_changed() -> bool {
+    DEVICES_CHANGED.swap(false, Ordering::Relaxed)
+}
+
+// Rescans devices, keeping the instances' device indices pointing at the same devices. Devices
+// that are gone are taken out of their instances, and instances left without any are removed.
+// Returns a message for each device that connected or disconnected.
+pub fn rescan_input_devices(
+    devices: &mut Vec<InputDevice>,
+    instances: &mut Vec<Instance>,
+    filter: &PadFilterType,
+) -> Vec<String> {
+    let new_devices = scan_input_devices(filter);
+    let mut messages = Vec::new();
+
+    // Where each old device ended up in the new list, matched by identity since a new device can
+    // reuse a freed path. The path only decides between nodes of one device that share an identity.
+    let mut taken = vec![false; new_devices.len()];
+    let mut new_index: Vec<Option<usize>> = Vec::new();
+    for dev in devices.iter() {
+        let id = dev.id();
+        let candidates: Vec<usize> = (0..new_devices.len())
+            .filter(|n| !taken[*n] && new_devices[*n].id() == id)
+            .collect();
+        let found = candidates
+            .iter()
+            .copied()
+            .find(|n| new_devices[*n].path() == dev.path())
+            .or(candidates.first().copied());
+        match found {
+            Some(n) => taken[n] = true,
+            None => messages.push(format!("{} {} disconnected", dev.emoji(), dev.fancyname())),
+        }
+        new_index.push(found);
+    }
+    for (n, dev) in new_devices.iter().enumerate() {
+        if !taken[n] {
+            messages.push(format!("{} {} connected", dev.emoji(), dev.fancyname()));
+        }
+    }
+
+    for instance in instances.iter_mut() {
+        instance.devices = instance
+            .devices
+            .iter()
+            .filter_map(|d| new_index.get(*d).copied().flatten())
+            .collect();
+    }
+    instances.retain(|instance| !instance.devices.is_empty());
+    *devices = new_devices;
+    messages
+}
+
+// Every device node belonging to the same physical device as an event node: its other event
+// nodes (motion sensors, touchpads), its legacy js nodes and its hidraw nodes. Resolved through
+// sysfs by finding the device's HID parent, or its USB interface for non-HID drivers.
+pub fn physical_device_nodes(event_path: &str) -> Vec<String> {
+    let Some(name) = std::path::Path::new(event_path).file_name() else {
+        return vec![event_path.to_string()];
+    };
+    let Some(root) = physical_root(&std::path::Path::new("/sys/class/input").join(name)) else {
+        return vec![event_path.to_string()];
+    };
+
+    let mut nodes = Vec::new();
+    for (class, dev_dir) in [("input", "/dev/input"), ("hidraw", "/dev")] {
+        let Ok(entries) = std::fs::read_dir(format!("/sys/class/{class}")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let node = entry.file_name().to_string_lossy().to_string();
+            if class == "input" && !node.starts_with("event") && !node.starts_with("js") {
                 continue;
             }
-            pads.push(InputDevice {
-                path: dev.0.to_str().unwrap().to_string(),
-                dev: dev.1,
-                enabled,
-                device_type,
-                has_button_held: false,
-            });
+            if std::fs::canonicalize(entry.path()).is_ok_and(|path| path.starts_with(&root)) {
+                nodes.push(format!("{dev_dir}/{node}"));
+            }
         }
     }
-    pads.sort_by_key(|pad| pad.path().to_string());
-    pads
+    if !nodes.iter().any(|node| node == event_path) {
+        nodes.push(event_path.to_string());
+    }
+    nodes.sort();
+    nodes
+}
+
+// The sysfs folder of the physical device an input class node belongs to
+fn physical_root(class_node: &std::path::Path) -> Option<std::path::PathBuf> {
+    // class node -> inputN -> the driver's device
+    let input = std::fs::canonicalize(class_node.join("device")).ok()?;
+    let parent = input.parent()?.to_path_buf();
+    let hid = parent.ancestors().find(|dir| {
+        std::fs::read_link(dir.join("subsystem"))
+            .is_ok_and(|subsystem| subsystem.file_name().is_some_and(|s| s == "hid"))
+    });
+    // Virtual devices sit directly under /sys/devices/virtual/input, which would cover them all
+    match hid {
+        Some(hid) => Some(hid.to_path_buf()),
+        None if parent.ends_with("virtual/input") => Some(input),
+        None => Some(parent),
+    }
 }
@@ -1,8 +1,11 @@
 use crate::GUEST_NAMES;
 use crate::app::PartyConfig;
+use crate::paths::PATH_PARTY;
 use crate::util::get_screen_resolution;
 
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Instance {
     pub devices: Vec<usize>,
     pub profname: String,
@@ -42,6 +45,8 @@ pub fn set_instance_resolutions(instances: &mut Vec<Instance>, cfg: &PartyConfig
 
 pub fn set_instance_names(instances: &mut Vec<Instance>, profiles: &[String]) {
     let mut guests = GUEST_NAMES.to_vec();
+    // Skip guest names already taken by a running session
+    guests.retain(|g| !PATH_PARTY.join(format!("profiles/.{g}")).exists());
 
     for instance in instances {
         if instance.profselection == 0 {
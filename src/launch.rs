@@ -1,4 +1,5 @@
 use std::path::PathBuf;
+use std::process::{Child, Command};
 
 use crate::app::PartyConfig;
 use crate::game::Game;
@@ -6,8 +7,17 @@ use crate::handler::*;
 use crate::input::*;
 use crate::instance::*;
 use crate::launch::Game::{ExecRef, HandlerRef};
+use crate::netns::*;
 use crate::paths::*;
+use crate::prefix::*;
+use crate::runner::*;
+use crate::runtime::resolve_runtime;
+use crate::sandbox::*;
+use crate::session::*;
 use crate::util::*;
+use crate::vpad::*;
+
+use std::os::unix::process::CommandExt;
 
 pub fn launch_game(
     game: &Game,
@@ -15,41 +25,285 @@ pub fn launch_game(
     instances: &Vec<Instance>,
     cfg: &PartyConfig,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    if session_running() {
+        return Err("A session is already running!".into());
+    }
+    if let Err(err) = save_device_assignments(instances, input_devices) {
+        println!("[PARTYDECK] Couldn't save device assignments: {err}");
+    }
+
     if let HandlerRef(h) = game {
+        if h.overlay_dir {
+            check_bwrap_overlay()?;
+        }
         for instance in instances {
             create_profile(instance.profname.as_str())?;
             create_gamesave(instance.profname.as_str(), &h)?;
+            if h.overlay_dir {
+                create_overlay_dirs(instance.profname.as_str(), &h)?;
+            }
         }
-        if h.symlink_dir {
+        if h.overlay_dir {
+            create_overlay_layer(&h)?;
+        } else if h.uses_symlink_dir() {
             create_symlink_folder(&h)?;
         }
+        if h.uses_instance_dirs() {
+            for (i, instance) in instances.iter().enumerate() {
+                create_instance_dir(&h, i, instance.profname.as_str())?;
+            }
+        }
     }
 
-    let cmd = launch_cmd(game, input_devices, instances, cfg)?;
-    println!("\nCOMMAND:\n{}\n", cmd);
+    // Virtual pads are created first, as the sandboxes are built around their nodes
+    let mut input_devices = input_devices.to_vec();
+    let vpads = create_virtual_pads(&mut input_devices, instances, cfg)?;
+    let input_devices = input_devices.as_slice();
 
-    if cfg.enable_kwin_script {
-        let script = if instances.len() == 2 && cfg.vertical_two_player {
-            "splitscreen_kwin_vertical.js"
-        } else {
-            "splitscreen_kwin.js"
+    let (netns, netns_child) = match cfg.isolated_network {
+        true => {
+            let (netns, child) = NetNamespace::start()?;
+            (Some(netns), Some(child))
+        }
+        false => (None, None),
+    };
+
+    let (cmds, kwin_script) =
+        match prepare_launch(game, input_devices, instances, cfg, netns.as_ref()) {
+            Ok(prepared) => prepared,
+            Err(err) => {
+                if let Some(netns) = &netns {
+                    netns.stop();
+                }
+                return Err(err);
+            }
         };
 
-        kwin_dbus_start_script(PATH_RES.join(script))?;
+    clear_end_request();
+    *SESSION.lock().unwrap() = Some(Session {
+        game: game.to_owned(),
+        cfg: cfg.clone(),
+        instances: instances.clone(),
+        input_devices: input_devices.to_vec(),
+        gamepads: session_gamepads(input_devices, instances),
+        pgids: Vec::new(),
+        children: netns_child.into_iter().collect(),
+        kwin_script,
+        netns,
+        vpads,
+    });
+
+    for (i, cmd) in cmds.iter().enumerate() {
+        if end_requested() {
+            break;
+        }
+        println!("\nCOMMAND (instance {}):\n{}\n", i + 1, cmd);
+        let child = match spawn_instance(cmd) {
+            Ok(child) => child,
+            Err(err) => {
+                end_session()?;
+                return Err(err);
+            }
+        };
+        if let Some(session) = SESSION.lock().unwrap().as_mut() {
+            session.pgids.push(child.id());
+            session.children.push(child);
+            write_session_lock(session)?;
+        }
+
+        if i < cmds.len() - 1 {
+            // Proton games need a ~5 second buffer in-between launches
+            // TODO: investigate why this is
+            std::thread::sleep(launch_delay(game));
+        }
+    }
+
+    std::thread::spawn(watch_session);
+
+    Ok(())
+}
+
+// Builds the instances' commands, readies their prefixes and starts the KWin script
+fn prepare_launch(
+    game: &Game,
+    input_devices: &[DeviceInfo],
+    instances: &Vec<Instance>,
+    cfg: &PartyConfig,
+    netns: Option<&NetNamespace>,
+) -> Result<(Vec<String>, Option<String>), Box<dyn std::error::Error>> {
+    let cmds = launch_cmd(game, input_devices, instances, cfg, netns)?;
+
+    if game.is_win() {
+        let runner = Runner::new(game, cfg)?;
+        for (i, instance) in instances.iter().enumerate() {
+            prepare_prefix(
+                &runner,
+                game,
+                cfg,
+                &instance_prefix(cfg, i, &instance.profname),
+            )?;
+        }
+    }
+
+    let kwin_script = match cfg.enable_kwin_script {
+        true => {
+            let script = kwin_script_name(instances.len(), cfg);
+            kwin_dbus_start_script(PATH_RES.join(script))?;
+            Some(script.to_string())
+        }
+        false => None,
+    };
+
+    Ok((cmds, kwin_script))
+}
+
+// Launches one more instance into the running session. The new instance's devices are
+// indices into input_devices, which may differ from the snapshot taken at launch.
+pub fn add_to_session(
+    input_devices: &[DeviceInfo],
+    instance: &Instance,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Copy what's needed out of the session, so it isn't locked while the new instance is set
+    // up; the GUI and the session watcher check it several times a second
+    let (game, cfg, mut devices, mut instances, netns, kwin_script) = {
+        let guard = SESSION.lock().unwrap();
+        let session = guard.as_ref().ok_or_else(|| "No session is running")?;
+        if session.instances.len() >= 4 {
+            return Err("The session already has the maximum of 4 players".into());
+        }
+        (
+            session.game.to_owned(),
+            session.cfg.clone(),
+            session.input_devices.clone(),
+            session.instances.clone(),
+            session.netns,
+            session.kwin_script.clone(),
+        )
+    };
+    let instance_count = instances.len();
+
+    // Remap the new instance's devices into the session's device snapshot
+    let mut instance = instance.clone();
+    for d in &mut instance.devices {
+        let info = &input_devices[*d];
+        *d = match find_device(&devices, &info.id, &info.path) {
+            Some(index) => index,
+            None => {
+                devices.push(info.clone());
+                devices.len() - 1
+            }
+        };
+    }
+    let vpads = create_virtual_pads(&mut devices, std::slice::from_ref(&instance), &cfg)?;
+
+    if let Err(err) = save_device_assignments(std::slice::from_ref(&instance), &devices) {
+        println!("[PARTYDECK] Couldn't save device assignments: {err}");
+    }
+
+    instances.push(instance);
+    set_instance_resolutions(&mut instances, &cfg);
+
+    let new = instances.last().unwrap();
+    if let HandlerRef(h) = &game {
+        create_profile(new.profname.as_str())?;
+        create_gamesave(new.profname.as_str(), h)?;
+        if h.overlay_dir {
+            create_overlay_dirs(new.profname.as_str(), h)?;
+        }
+        if h.uses_instance_dirs() {
+            create_instance_dir(h, instances.len() - 1, new.profname.as_str())?;
+        }
+    }
+
+    let cmd = launch_cmd(&game, &devices, &instances, &cfg, netns.as_ref())?
+        .pop()
+        .ok_or_else(|| "Couldn't build launch command")?;
+
+    if game.is_win() {
+        let runner = Runner::new(&game, &cfg)?;
+        let pfx = instance_prefix(&cfg, instances.len() - 1, &new.profname);
+        prepare_prefix(&runner, &game, &cfg, &pfx)?;
+    }
+
+    // The KWin script lays out every gamescope window again when the new one appears,
+    // but the script itself has to be swapped when leaving the vertical two-player layout
+    let mut kwin_script = kwin_script;
+    if let Some(script) = &kwin_script {
+        let new_script = kwin_script_name(instances.len(), &cfg);
+        if script != new_script {
+            kwin_dbus_unload_script()?;
+            kwin_dbus_start_script(PATH_RES.join(new_script))?;
+            kwin_script = Some(new_script.to_string());
+        }
+    }
+
+    let mut guard = SESSION.lock().unwrap();
+    let session = guard
+        .as_mut()
+        .ok_or_else(|| "The session ended while the player was joining")?;
+    if session.instances.len() >= 4 {
+        return Err("The session already has the maximum of 4 players".into());
+    }
+    if session.instances.len() != instance_count {
+        return Err("The session changed while the player was joining".into());
     }
 
-    std::process::Command::new("sh")
+    // Spawning is quick, and doing it only now means a failed check above leaves nothing running
+    println!("\nCOMMAND (instance {}):\n{}\n", instances.len(), cmd);
+    let child = spawn_instance(&cmd)?;
+    session.pgids.push(child.id());
+    session.children.push(child);
+    session.gamepads = session_gamepads(&devices, &instances);
+    session.input_devices = devices;
+    session.instances = instances;
+    session.vpads.extend(vpads);
+    session.kwin_script = kwin_script;
+    write_session_lock(session)?;
+
+    Ok(())
+}
+
+// Runs an instance command in its own process group, so the whole tree can be signalled at once
+fn spawn_instance(cmd: &str) -> Result<Child, Box<dyn std::error::Error>> {
+    let child = Command::new("sh")
         .arg("-c")
         .arg(cmd)
-        .status()?;
+        .process_group(0)
+        .spawn()?;
+    Ok(child)
+}
 
-    if cfg.enable_kwin_script {
-        kwin_dbus_unload_script()?;
+pub fn kwin_script_name(playercount: usize, cfg: &PartyConfig) -> &'static str {
+    if playercount == 2 && cfg.vertical_two_player {
+        "splitscreen_kwin_vertical.js"
+    } else {
+        "splitscreen_kwin.js"
     }
+}
 
-    remove_guest_profiles()?;
+fn launch_delay(game: &Game) -> std::time::Duration {
+    match game.is_win() {
+        true => std::time::Duration::from_secs(6),
+        false => std::time::Duration::from_millis(10),
+    }
+}
 
-    Ok(())
+// Paths of the gamepads in use this session, watched for the end-session combo. Virtual pads
+// are watched instead of their controllers, as they keep their path across reconnects.
+fn session_gamepads(input_devices: &[DeviceInfo], instances: &Vec<Instance>) -> Vec<String> {
+    let mut out = Vec::new();
+    for instance in instances {
+        for d in &instance.devices {
+            let dev = &input_devices[*d];
+            if dev.device_type == DeviceType::Gamepad {
+                let path = dev.virtual_path.as_ref().unwrap_or(&dev.path);
+                if !out.contains(path) {
+                    out.push(path.clone());
+                }
+            }
+        }
+    }
+    out
 }
 
 pub fn launch_cmd(
@@ -57,7 +311,8 @@ pub fn launch_cmd(
     input_devices: &[DeviceInfo],
     instances: &Vec<Instance>,
     cfg: &PartyConfig,
-) -> Result<String, Box<dyn std::error::Error>> {
+    netns: Option<&NetNamespace>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     let home = PATH_HOME.display();
     let localshare = PATH_LOCAL_SHARE.display();
     let party = PATH_PARTY.display();
@@ -71,23 +326,24 @@ pub fn launch_cmd(
                 .ok_or_else(|| "Invalid path")?
                 .to_string_lossy()
         ),
-        HandlerRef(h) => match h.symlink_dir {
-            true => &format!("{party}/gamesyms/{}", h.uid),
+        HandlerRef(h) => match h.uses_symlink_dir() {
+            true => &symlink_gamedir(h).display().to_string(),
             false => &get_rootpath_handler(&h)?,
         },
     };
 
-    let win = match game {
-        ExecRef(e) => e.path().extension().unwrap_or_default() == "exe",
-        HandlerRef(h) => h.win,
+    let win = game.is_win();
+    let runner = match win {
+        true => Some(Runner::new(game, cfg)?),
+        false => None,
     };
 
-    let mut cmd = String::new();
-    // Command: "gamescope [settings] -- bwrap [binds] [runtime] [exec] [args] & ..."
-    cmd.push_str("export ");
-    cmd.push_str("SDL_JOYSTICK_HIDAPI=0 ");
-    cmd.push_str("ENABLE_GAMESCOPE_WSI=0 ");
-    cmd.push_str("PROTON_DISABLE_HIDRAW=1 ");
+    let mut env = String::new();
+    // Command per instance: "export [env]; cd [gamedir]; gamescope [settings] -- bwrap [binds] [runtime] [exec] [args]"
+    env.push_str("export ");
+    env.push_str("SDL_JOYSTICK_HIDAPI=0 ");
+    env.push_str("ENABLE_GAMESCOPE_WSI=0 ");
+    env.push_str("PROTON_DISABLE_HIDRAW=1 ");
 
     if cfg.force_sdl && !win {
         let mut path_sdl = "/ubuntu12_32/steam-runtime/usr/lib/x86_64-linux-gnu/libSDL2-2.0.so.0";
@@ -96,47 +352,70 @@ pub fn launch_cmd(
                 path_sdl = "/ubuntu12_32/steam-runtime/usr/lib/i386-linux-gnu/libSDL2-2.0.so.0";
             }
         };
-        cmd.push_str(&format!("SDL_DYNAMIC_API=\"{steam}/{path_sdl}\" "));
+        env.push_str(&format!("SDL_DYNAMIC_API=\"{steam}/{path_sdl}\" "));
     }
-    if win {
-        let protonpath = match cfg.proton_version.is_empty() {
-            true => "GE-Proton",
-            false => cfg.proton_version.as_str(),
-        };
-        cmd.push_str(&format!("PROTON_VERB=run "));
-        cmd.push_str(&format!("PROTONPATH={protonpath} "));
+    if let Some(runner) = &runner {
+        env.push_str(&runner.env());
 
+        let mut dll_overrides = runner.dll_overrides();
         if let HandlerRef(h) = game {
-            if !h.dll_overrides.is_empty() {
-                cmd.push_str("WINEDLLOVERRIDES=\"");
-                for dll in &h.dll_overrides {
-                    cmd.push_str(&format!("{dll},"));
-                }
-                cmd.push_str("=n,b\" ");
-            }
+            dll_overrides.extend(h.dll_overrides.iter().cloned());
+        }
+        if !dll_overrides.is_empty() {
+            let overrides: Vec<String> = dll_overrides
+                .iter()
+                .map(|(dll, mode)| format!("{dll}={mode}"))
+                .collect();
+            env.push_str(&format!("WINEDLLOVERRIDES=\"{}\" ", overrides.join(";")));
+        }
+
+        if let (HandlerRef(h), Runner::Umu { .. }) = (game, runner) {
             if h.coldclient {
-                cmd.push_str("PROTON_DISABLE_LSTEAMCLIENT=1 ");
+                env.push_str("PROTON_DISABLE_LSTEAMCLIENT=1 ");
+            }
+            // Lets umu apply the same protonfixes Steam would for this game
+            if let Some(gameid) = h.umu_gameid() {
+                env.push_str(&format!("GAMEID=\"{gameid}\" "));
+            }
+            if let Some(store) = h.umu_store() {
+                env.push_str(&format!("STORE=\"{store}\" "));
+            }
+            if cfg.game_settings(&h.uid).disable_protonfixes {
+                env.push_str("PROTONFIXES_DISABLE=1 ");
             }
         }
     }
-    cmd.push_str("; ");
-
-    let runtime = match win {
-        // UMU CHANGE
-        true => &format!("{}", BIN_UMU_RUN.to_string_lossy()),
-        false => {
-            if let HandlerRef(h) = game {
-                match h.runtime.as_str() {
-                    "scout" => &format!("\"{steam}/ubuntu12_32/steam-runtime/run.sh\""),
-                    "soldier" => &format!(
-                        "\"{steam}/steamapps/common/SteamLinuxRuntime_soldier/_v2-entry-point\""
-                    ),
-                    _ => "",
+    env.push_str("; ");
+
+    // Paths stricter sandbox policies have to expose besides the instance's game folder
+    let mut sandbox_writable = default_writable_paths();
+    let mut sandbox_readable = default_readable_paths();
+    if let HandlerRef(h) = game {
+        if h.uses_symlink_dir() || h.overlay_dir {
+            sandbox_readable.push(get_rootpath_handler(h)?);
+        }
+    }
+    if let Some(runner) = &runner {
+        sandbox_readable.extend(runner.sandbox_paths());
+    }
+
+    let runtime = match (&runner, game) {
+        (Some(runner), _) => &runner.command(),
+        (None, HandlerRef(h)) => match resolve_runtime(h)? {
+            Some((_, path)) => {
+                // pressure-vessel writes into the runtime's folder when it starts
+                if let Some(dir) = path.parent() {
+                    sandbox_writable.push(dir.display().to_string());
                 }
-            } else {
-                ""
+                &format!("\"{}\"", path.display())
             }
-        }
+            None => "",
+        },
+        (None, ExecRef(_)) => "",
+    };
+    let policy = match game {
+        HandlerRef(h) => cfg.sandbox_for(h),
+        ExecRef(_) => SandboxPolicy::Permissive,
     };
 
     let exec = match game {
@@ -148,33 +427,34 @@ pub fn launch_cmd(
         return Err(format!("Executable not found: {gamedir}/{exec}").into());
     }
 
-    if let HandlerRef(h) = game {
-        if h.runtime == "scout" && !PATH_STEAM.join("ubuntu12_32/steam-runtime/run.sh").exists() {
-            return Err("Steam Scout Runtime not found".into());
-        } else if h.runtime == "soldier"
-            && !PATH_STEAM
-                .join("steamapps/common/SteamLinuxRuntime_soldier")
-                .exists()
-        {
-            return Err("Steam Soldier Runtime not found".into());
-        }
-    }
-
-    cmd.push_str(&format!("cd \"{gamedir}\"; "));
+    // All nodes of each device's physical device, looked up once for every instance
+    let device_nodes: Vec<Vec<String>> = input_devices
+        .iter()
+        .map(|dev| physical_device_nodes(&dev.path))
+        .collect();
 
+    let mut cmds = Vec::new();
     for (i, instance) in instances.iter().enumerate() {
+        let mut cmd = env.clone();
+        let gamedir = match game {
+            HandlerRef(h) if h.uses_instance_dirs() => {
+                &instance_gamedir(h, i).display().to_string()
+            }
+            _ => gamedir,
+        };
+        cmd.push_str(&format!("cd \"{gamedir}\"; "));
+
         let path_prof = &format!("{party}/profiles/{}", instance.profname.as_str());
         let path_save = match game {
             ExecRef(_) => "",
             HandlerRef(h) => &format!("{path_prof}/saves/{}", h.uid.as_str()),
         };
 
-        let pfx = match cfg.proton_separate_pfxs {
-            true => &format!("{party}/pfx{}", i + 1),
-            false => &format!("{party}/pfx"),
-        };
+        let pfx = &instance_prefix(cfg, i, &instance.profname)
+            .display()
+            .to_string();
         if win {
-            cmd.push_str(&format!("WINEPREFIX={pfx} "));
+            cmd.push_str(&format!("WINEPREFIX=\"{pfx}\" "));
         }
 
         let (gsc_width, gsc_height) = (instance.width, instance.height);
@@ -199,15 +479,17 @@ pub fn launch_cmd(
             let mut kbms = String::new();
 
             for d in &instance.devices {
-                if input_devices[*d].device_type == DeviceType::Keyboard {
+                let dev = &input_devices[*d];
+                if dev.device_type == DeviceType::Keyboard {
                     instance_has_keyboard = true;
-                } else if input_devices[*d].device_type == DeviceType::Mouse {
+                } else if dev.device_type.is_pointer() {
                     instance_has_mouse = true;
                 }
-                if input_devices[*d].device_type == DeviceType::Keyboard
-                    || input_devices[*d].device_type == DeviceType::Mouse
-                {
-                    kbms.push_str(&format!("{},", input_devices[*d].path));
+                if dev.device_type == DeviceType::Keyboard || dev.device_type.is_pointer() {
+                    // A keyboard's extra nodes carry its media keys and, on some, half its keys
+                    for path in std::iter::once(&dev.path).chain(&dev.siblings) {
+                        kbms.push_str(&format!("{path},"));
+                    }
                 }
             }
 
@@ -224,58 +506,113 @@ pub fn launch_cmd(
 
         cmd.push_str(&format!("-- "));
 
-        cmd.push_str(&format!(
-            "bwrap --die-with-parent --dev-bind / / --tmpfs /tmp "
-        ));
+        if let Some(netns) = netns {
+            cmd.push_str(&netns.enter_cmd());
+        }
 
-        // Bind player profile directories to the game's directories
-        let mut binds = String::new();
+        let mut writable = sandbox_writable.clone();
+        writable.push(gamedir.to_string());
+        let mut sandbox = Sandbox::new(policy, &writable, &sandbox_readable);
+        if netns.is_some() {
+            // X clients can't reach gamescope's abstract socket from another network namespace,
+            // so they need the socket files that the /tmp tmpfs would hide
+            sandbox.mount(Mount::BindTry(
+                "/tmp/.X11-unix".to_string(),
+                "/tmp/.X11-unix".to_string(),
+            ));
+        }
 
-        // Mask out any gamepads that aren't this player's
+        // Overlay mode mounts the game folder over itself, with the handler's layer on top of the
+        // game's files and the profile's writable layer above both
+        if let HandlerRef(h) = game {
+            if h.overlay_dir {
+                let path_overlay = format!("{path_prof}/overlay/{}", h.uid);
+                sandbox.mount(Mount::Overlay {
+                    lower: vec![gamedir.to_string(), format!("{party}/gamelayers/{}", h.uid)],
+                    upper: format!("{path_overlay}/upper"),
+                    work: format!("{path_overlay}/work"),
+                    dest: gamedir.to_string(),
+                });
+                sandbox.chdir(gamedir);
+            }
+        }
+
+        // Mask out every node of the gamepads that aren't this player's. Nodes shared with one
+        // of this player's devices stay visible. With virtual pads the player's own gamepads are
+        // masked too, leaving only their virtual pads, and /dev/input is emptied so controllers
+        // plugged in later don't show up either.
+        let mut masked: Vec<String> = Vec::new();
+        let mut own: Vec<String> = Vec::new();
         for (d, dev) in input_devices.iter().enumerate() {
-            if !dev.enabled
-                || (!instance.devices.contains(&d) && dev.device_type == DeviceType::Gamepad)
-            {
-                let path = &dev.path;
-                binds.push_str(&format!("--bind /dev/null {path} "));
+            let mine = instance.devices.contains(&d);
+            if let (true, Some(virtual_path)) = (mine, &dev.virtual_path) {
+                masked.extend(device_nodes[d].iter().cloned());
+                own.push(virtual_path.clone());
+            } else if !dev.enabled || (!mine && dev.device_type.is_controller()) {
+                masked.extend(device_nodes[d].iter().cloned());
+                masked.extend(dev.virtual_path.iter().cloned());
+            } else if mine {
+                own.extend(device_nodes[d].iter().cloned());
             }
         }
+        masked.sort();
+        masked.dedup();
+        masked.retain(|node| !own.contains(node));
+        if cfg.virtual_pads {
+            sandbox.mount(Mount::Tmpfs("/dev/input".to_string()));
+            masked.retain(|node| !node.starts_with("/dev/input/"));
+            for node in own.iter().filter(|node| node.starts_with("/dev/input/")) {
+                sandbox.mount(Mount::DevBind(node.clone(), node.clone()));
+            }
+        }
+        for node in &masked {
+            sandbox.bind("/dev/null", node);
+        }
 
+        // Bind player profile directories to the game's directories
         if let HandlerRef(h) = game {
             let path_goldberg = h.path_goldberg.as_str();
             if !path_goldberg.is_empty() {
-                binds.push_str(&format!(
-                    "--bind \"{path_prof}/steam\" \"{gamedir}/{path_goldberg}/goldbergsave\" "
-                ));
+                sandbox.bind(
+                    &format!("{path_prof}/steam"),
+                    &format!("{gamedir}/{path_goldberg}/goldbergsave"),
+                );
+                if netns.is_some() {
+                    sandbox.bind(
+                        &loopback_broadcasts_file(h)?.display().to_string(),
+                        &format!("{gamedir}/{path_goldberg}/steam_settings/custom_broadcasts.txt"),
+                    );
+                }
             }
-            if h.win {
-                let path_windata = format!("{pfx}/drive_c/users/steamuser/");
+            if let Some(runner) = &runner {
+                let path_windata = format!("{pfx}/drive_c/users/{}/", runner.wine_user());
                 if h.win_unique_appdata {
-                    binds.push_str(&format!(
-                        "--bind \"{path_save}/_AppData\" \"{path_windata}/AppData\" "
-                    ));
+                    sandbox.bind(
+                        &format!("{path_save}/_AppData"),
+                        &format!("{path_windata}/AppData"),
+                    );
                 }
                 if h.win_unique_documents {
-                    binds.push_str(&format!(
-                        "--bind \"{path_save}/_Documents\" \"{path_windata}/Documents\" "
-                    ));
+                    sandbox.bind(
+                        &format!("{path_save}/_Documents"),
+                        &format!("{path_windata}/Documents"),
+                    );
                 }
             } else {
                 if h.linux_unique_localshare {
-                    binds.push_str(&format!("--bind \"{path_save}/_share\" \"{localshare}\" "));
-                    binds.push_str(&format!("--bind {party} {party} "));
-                    binds.push_str(&format!("--bind {steam} {steam} "));
+                    sandbox.bind(&format!("{path_save}/_share"), &localshare.to_string());
+                    sandbox.bind(&party.to_string(), &party.to_string());
+                    sandbox.bind(&steam.to_string(), &steam.to_string());
                 }
                 if h.linux_unique_config {
-                    binds.push_str(&format!(
-                        "--bind \"{path_save}/_config\" \"{home}/.config\" "
-                    ));
+                    sandbox.bind(&format!("{path_save}/_config"), &format!("{home}/.config"));
                 }
             }
             for subdir in &h.game_unique_paths {
-                binds.push_str(&format!(
-                    "--bind \"{path_save}/{subdir}\" \"{gamedir}/{subdir}\" "
-                ));
+                sandbox.bind(
+                    &format!("{path_save}/{subdir}"),
+                    &format!("{gamedir}/{subdir}"),
+                );
             }
         }
 
@@ -295,17 +632,13 @@ pub fn launch_cmd(
             ExecRef(e) => e.args.clone().sanitize_path(),
         };
 
-        cmd.push_str(&format!("{binds} {runtime} \"{gamedir}/{exec}\" {args} "));
+        cmd.push_str(&format!(
+            "{} {runtime} \"{gamedir}/{exec}\" {args} ",
+            sandbox.to_args()
+        ));
 
-        if i < instances.len() - 1 {
-            // Proton games need a ~5 second buffer in-between launches
-            // TODO: investigate why this is
-            match win {
-                true => cmd.push_str("& sleep 6; "),
-                false => cmd.push_str("& sleep 0.01; "),
-            }
-        }
+        cmds.push(cmd);
     }
 
-    Ok(cmd)
+    Ok(cmds)
 }
@@ -1,14 +1,25 @@
 mod app;
+mod bindings;
 mod game;
 mod handler;
 mod input;
 mod instance;
 mod launch;
+mod netns;
 mod paths;
+mod prefix;
+mod proton;
+mod remap;
+mod runner;
+mod runtime;
+mod sandbox;
+mod session;
 mod util;
+mod vpad;
 
 use crate::app::*;
 use crate::paths::PATH_PARTY;
+use crate::session::*;
 use crate::util::*;
 
 fn main() -> eframe::Result {
@@ -19,6 +30,16 @@ fn main() -> eframe::Result {
         std::process::exit(0);
     }
 
+    if std::env::args().any(|arg| arg == "--end-session") {
+        match signal_end_session() {
+            Ok(_) => std::process::exit(0),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     if std::env::args().any(|arg| arg == "--kwin") {
         let args: Vec<String> = std::env::args().filter(|arg| arg != "--kwin").collect();
 
@@ -77,12 +98,57 @@ fn main() -> eframe::Result {
     std::fs::create_dir_all(PATH_PARTY.join("profiles"))
         .expect("Failed to create profiles directory");
 
-    remove_guest_profiles().unwrap();
+    // Recover from a session left behind by a PartyDeck that crashed or was killed
+    let mut keep_session_data = false;
+    if let Some(lock) = read_session_lock() {
+        if lock.owner_alive() {
+            println!("[PARTYDECK] Another PartyDeck is running a session, leaving it alone");
+            keep_session_data = true;
+        } else if lock.instances_alive() && !lock.can_reattach() {
+            if exec.is_empty() {
+                msg(
+                    "Previous Session Found",
+                    &format!(
+                        "A previous session of {} is still running, but the virtual controllers its instances read from closed along with PartyDeck, so it can't be reattached. Its instances will be closed.",
+                        lock.game_name()
+                    ),
+                );
+            }
+            cleanup_stale_session(lock).unwrap();
+        } else if lock.instances_alive()
+            && exec.is_empty()
+            && yesno(
+                "Previous Session Found",
+                &format!(
+                    "A previous session of {} is still running. Reattach to it?\n\nChoosing \"No\" will close its instances and clean up.",
+                    lock.game_name()
+                ),
+            )
+        {
+            match reattach_session(lock) {
+                Ok(_) => keep_session_data = true,
+                Err(err) => {
+                    msg("Error", &format!("Couldn't reattach to session: {err}"));
+                    if let Some(lock) = read_session_lock() {
+                        cleanup_stale_session(lock).unwrap();
+                    }
+                }
+            }
+        } else {
+            cleanup_stale_session(lock).unwrap();
+        }
+    }
 
-    if PATH_PARTY.join("tmp").exists() {
-        std::fs::remove_dir_all(PATH_PARTY.join("tmp")).unwrap();
+    if !keep_session_data {
+        remove_guest_profiles().unwrap();
+
+        if PATH_PARTY.join("tmp").exists() {
+            std::fs::remove_dir_all(PATH_PARTY.join("tmp")).unwrap();
+        }
     }
 
+    install_signal_handlers();
+
     let (_, scrheight) = get_screen_resolution();
 
     let scale = match fullscreen {
@@ -135,4 +201,5 @@ Options:
     --args [args]         Specify arguments for the executable to be launched with. Must be quoted if containing spaces.
     --fullscreen          Start the GUI in fullscreen mode
     --kwin                Launch PartyDeck inside of a KWin session
+    --end-session         Close all instances of the session started by a running PartyDeck
 "#;
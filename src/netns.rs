@@ -0,0 +1,104 @@
+use crate::handler::Handler;
+use crate::paths::*;
+use crate::util::*;
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+
+// A network namespace with only loopback, kept alive by a holder process for as long as the
+// session runs. Instances join it, so they can reach each other on 127.0.0.1 but nothing else.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct NetNamespace {
+    // Process group of the holder, killed to tear the namespace down
+    pub pgid: u32,
+    // Process inside the namespace that instances enter through
+    pub pid: u32,
+    // Start time of the holder, so a process that reused its pgid is never killed
+    #[serde(default)]
+    pub starttime: u64,
+}
+
+impl NetNamespace {
+    // bwrap creates the user and network namespaces and brings loopback up. The holder isn't
+    // tied to its parent, as that's the thread that launched the session; ending the session or
+    // cleaning it up after a crash kills it instead.
+    pub fn start() -> Result<(Self, Child), Box<dyn Error>> {
+        let child = Command::new("bwrap")
+            .args(["--dev-bind", "/", "/", "--unshare-user", "--unshare-net"])
+            .args(["sleep", "infinity"])
+            .process_group(0)
+            .spawn()?;
+        let pgid = child.id();
+
+        let own_ns = std::fs::read_link("/proc/self/ns/net")?;
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            let inside = process_tree(pgid).into_iter().find(|pid| {
+                std::fs::read_link(format!("/proc/{pid}/ns/net")).is_ok_and(|ns| ns != own_ns)
+            });
+            if let Some(pid) = inside {
+                println!("[PARTYDECK] Started session network namespace (pid {pid})");
+                let starttime = process_starttime(pgid).unwrap_or_default();
+                return Ok((
+                    NetNamespace {
+                        pgid,
+                        pid,
+                        starttime,
+                    },
+                    child,
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        signal_group(pgid, libc::SIGKILL);
+        Err("Couldn't create the session network namespace".into())
+    }
+
+    // Whether the holder that was started is still the process with its pgid
+    pub fn holder_alive(&self) -> bool {
+        process_starttime(self.pgid) == Some(self.starttime)
+    }
+
+    pub fn stop(&self) {
+        if self.holder_alive() {
+            signal_group(self.pgid, libc::SIGKILL);
+        }
+    }
+
+    // Prefix that runs a command inside the namespace, keeping the user's own uid
+    pub fn enter_cmd(&self) -> String {
+        format!(
+            "nsenter --target {} --user --net --preserve-credentials ",
+            self.pid
+        )
+    }
+}
+
+// Goldberg finds other players by broadcasting, which has nowhere to go with only loopback,
+// so it's pointed at 127.0.0.1 instead, along with any addresses the handler's own file lists
+pub fn loopback_broadcasts_file(h: &Handler) -> Result<PathBuf, Box<dyn Error>> {
+    let shipped = h
+        .path_handler
+        .join("copy_to_symdir")
+        .join(&h.path_goldberg)
+        .join("steam_settings/custom_broadcasts.txt");
+    let mut lines: Vec<String> = std::fs::read_to_string(shipped)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if !lines.iter().any(|line| line == "127.0.0.1") {
+        lines.push("127.0.0.1".to_string());
+    }
+
+    let path = PATH_PARTY.join(format!("broadcasts/{}.txt", h.uid));
+    std::fs::create_dir_all(PATH_PARTY.join("broadcasts"))?;
+    std::fs::write(&path, lines.join("\n") + "\n")?;
+    Ok(path)
+}
@@ -16,6 +16,13 @@ pub static PATH_HOME: LazyLock<PathBuf> =
 
 pub static PATH_LOCAL_SHARE: LazyLock<PathBuf> = LazyLock::new(|| PATH_HOME.join(".local/share"));
 
+pub static PATH_CACHE: LazyLock<PathBuf> = LazyLock::new(|| {
+    if let Ok(xdg_cache_home) = env::var("XDG_CACHE_HOME") {
+        return PathBuf::from(xdg_cache_home);
+    }
+    PATH_HOME.join(".cache")
+});
+
 pub static PATH_PARTY: LazyLock<PathBuf> = LazyLock::new(|| {
     if let Ok(xdg_data_home) = env::var("XDG_DATA_HOME") {
         return PathBuf::from(xdg_data_home).join("partydeck");
@@ -0,0 +1,295 @@
+use crate::app::PartyConfig;
+use crate::game::Game;
+use crate::handler::{Handler, SetupStep};
+use crate::paths::*;
+use crate::runner::Runner;
+use crate::util::copy_paths;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+// Freshly initialized prefix that per-instance prefixes are cloned from
+const TEMPLATE_NAME: &str = "pfx_template";
+// Records which runner the template was initialized with
+const TEMPLATE_MARKER: &str = ".partydeck_template";
+// Added to a prefix's folder name while it's being cloned
+const PARTIAL_EXTENSION: &str = "partial";
+// Records which handler setup steps have been run in a prefix
+const SETUP_STATE: &str = ".partydeck_setup.json";
+// The last registry file imported for each handler is kept in the prefix as <uid> + this
+const REGISTRY_SUFFIX: &str = ".partydeck.reg";
+
+// Sizes of the prefixes last scanned, filled in by a background thread as they're measured
+static PREFIX_SIZES: LazyLock<Mutex<HashMap<PathBuf, u64>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+// Bumped on every scan so a slower, older measurement doesn't overwrite a newer one
+static SIZE_SCAN: AtomicU64 = AtomicU64::new(0);
+
+// Setup steps done for one handler, keyed by SetupStep::key
+#[derive(Serialize, Deserialize, Default)]
+struct SetupState {
+    version: String,
+    done: Vec<String>,
+}
+
+pub struct PrefixInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub version: String,
+}
+
+// Prefix used by the given instance slot. Profile prefixes live in the profile's folder,
+// so guests' prefixes are erased along with their profiles.
+pub fn instance_prefix(cfg: &PartyConfig, i: usize, profname: &str) -> PathBuf {
+    match (cfg.proton_separate_pfxs, cfg.proton_profile_pfxs) {
+        (true, true) => PATH_PARTY.join("profiles").join(profname).join("pfx"),
+        (true, false) => PATH_PARTY.join(format!("pfx{}", i + 1)),
+        (false, _) => PATH_PARTY.join("pfx"),
+    }
+}
+
+fn prefix_initialized(pfx: &Path) -> bool {
+    pfx.join("system.reg").exists()
+}
+
+// Makes sure a prefix is ready to launch into. Separate instance prefixes are cloned
+// from the template instead of each being initialized from scratch.
+pub fn prepare_prefix(
+    runner: &Runner,
+    game: &Game,
+    cfg: &PartyConfig,
+    pfx: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if !prefix_initialized(pfx) {
+        match cfg.proton_separate_pfxs {
+            true => {
+                let template = prepare_template(runner)?;
+                clone_prefix(&template, pfx)?;
+            }
+            false => runner.init_prefix(pfx)?,
+        }
+    }
+    runner.setup_prefix(pfx)?;
+    if let Game::HandlerRef(h) = game {
+        run_setup_steps(runner, h, pfx)?;
+        apply_registry(runner, h, pfx)?;
+    }
+    Ok(())
+}
+
+// Runs the handler's setup steps that haven't been done in this prefix yet. Every step
+// runs again when the handler's version changes.
+fn run_setup_steps(runner: &Runner, h: &Handler, pfx: &Path) -> Result<(), Box<dyn Error>> {
+    let steps = h.setup_steps();
+    if steps.is_empty() {
+        return Ok(());
+    }
+
+    let state_path = pfx.join(SETUP_STATE);
+    let mut states: HashMap<String, SetupState> = std::fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    let state = states.entry(h.uid.clone()).or_default();
+    if state.version != h.version {
+        state.version = h.version.clone();
+        state.done.clear();
+    }
+
+    for step in steps {
+        let key = step.key();
+        if state.done.contains(&key) {
+            continue;
+        }
+        println!("Running setup step \"{key}\" in {}", pfx.display());
+        let result = match &step {
+            SetupStep::Winetricks(verb) => runner.winetricks(pfx, verb),
+            SetupStep::RegImport(path) => {
+                runner.run_in_prefix(pfx, "regedit", &["/S".to_string(), windows_path(path)])
+            }
+            SetupStep::Installer(path, args) => {
+                runner.run_in_prefix(pfx, &path.to_string_lossy(), args)
+            }
+        };
+        if let Err(err) = result {
+            // Keep what's done so far so only the failed step onwards runs next time
+            std::fs::write(&state_path, serde_json::to_string_pretty(&states)?)?;
+            return Err(format!("Setup step \"{key}\" failed: {err}").into());
+        }
+        state.done.push(key);
+    }
+
+    std::fs::write(&state_path, serde_json::to_string_pretty(&states)?)?;
+    Ok(())
+}
+
+// Imports the handler's registry values, skipping the import if they haven't changed
+// since they were last applied to this prefix
+fn apply_registry(runner: &Runner, h: &Handler, pfx: &Path) -> Result<(), Box<dyn Error>> {
+    let Some(contents) = h.registry_file() else {
+        return Ok(());
+    };
+    let path = pfx.join(format!("{}{REGISTRY_SUFFIX}", h.uid));
+    if std::fs::read_to_string(&path).is_ok_and(|applied| applied == contents) {
+        return Ok(());
+    }
+
+    println!("Applying registry values for {} to {}", h.uid, pfx.display());
+    let pending = pfx.join(format!("{}{REGISTRY_SUFFIX}.new", h.uid));
+    std::fs::write(&pending, &contents)?;
+    let result = runner.run_in_prefix(pfx, "regedit", &["/S".to_string(), windows_path(&pending)]);
+    match result {
+        Ok(()) => std::fs::rename(&pending, &path)?,
+        Err(err) => {
+            let _ = std::fs::remove_file(&pending);
+            return Err(format!("Couldn't apply registry values: {err}").into());
+        }
+    }
+    Ok(())
+}
+
+// The path as seen from inside the prefix, through Wine's Z: drive
+fn windows_path(path: &Path) -> String {
+    format!("Z:{}", path.to_string_lossy().replace('/', "\\"))
+}
+
+// Initializes the template prefix, redoing it if it was made with a different runner
+fn prepare_template(runner: &Runner) -> Result<PathBuf, Box<dyn Error>> {
+    let template = PATH_PARTY.join(TEMPLATE_NAME);
+    let marker = template.join(TEMPLATE_MARKER);
+    let made_with = std::fs::read_to_string(&marker).unwrap_or_default();
+
+    if !prefix_initialized(&template) || made_with != runner.id() {
+        if template.exists() {
+            std::fs::remove_dir_all(&template)?;
+        }
+        runner.init_prefix(&template)?;
+        std::fs::write(&marker, runner.id())?;
+    }
+    Ok(template)
+}
+
+// Copies a prefix; the copy engine shares file data through reflinks where it can. The copy is
+// made beside dest and only moved into place once it's complete, so a cancelled or failed
+// clone never leaves a prefix that looks initialized.
+fn clone_prefix(src: &Path, dest: &Path) -> Result<(), Box<dyn Error>> {
+    println!("Cloning prefix {} to {}", src.display(), dest.display());
+    let partial = dest.with_extension(PARTIAL_EXTENSION);
+    if partial.exists() {
+        std::fs::remove_dir_all(&partial)?;
+    }
+    if let Err(err) = copy_paths(&[(src.to_path_buf(), partial.clone())]) {
+        let _ = std::fs::remove_dir_all(&partial);
+        return Err(err);
+    }
+
+    let _ = std::fs::remove_file(partial.join(TEMPLATE_MARKER));
+    // umu links pfx to the prefix itself; point the copy's link at the copy
+    let pfx_link = partial.join("pfx");
+    if pfx_link.is_symlink() {
+        std::fs::remove_file(&pfx_link)?;
+        std::os::unix::fs::symlink(".", &pfx_link)?;
+    }
+
+    if dest.exists() {
+        std::fs::remove_dir_all(dest)?;
+    }
+    std::fs::rename(&partial, dest)?;
+    Ok(())
+}
+
+// Lists the template, every slot prefix and every profile's prefix. Guest prefixes are left out
+// since they only last for a session.
+pub fn scan_prefixes() -> Vec<PrefixInfo> {
+    let mut out = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(&*PATH_PARTY) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with("pfx")
+                && !name.ends_with(PARTIAL_EXTENSION)
+                && entry.path().is_dir()
+            {
+                out.push(prefix_info(name, entry.path()));
+            }
+        }
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut profile_pfxs = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(PATH_PARTY.join("profiles")) {
+        for entry in entries.flatten() {
+            let profname = entry.file_name().to_string_lossy().to_string();
+            let path = entry.path().join("pfx");
+            if !profname.starts_with('.') && path.is_dir() {
+                profile_pfxs.push(prefix_info(format!("{profname} (profile)"), path));
+            }
+        }
+    }
+    profile_pfxs.sort_by(|a, b| a.name.cmp(&b.name));
+    out.extend(profile_pfxs);
+
+    measure_prefixes(out.iter().map(|pfx| pfx.path.clone()).collect());
+    out
+}
+
+// Walking a prefix takes a while, so sizes are measured off the GUI thread
+fn measure_prefixes(paths: Vec<PathBuf>) {
+    let scan = SIZE_SCAN.fetch_add(1, Ordering::SeqCst) + 1;
+    PREFIX_SIZES.lock().unwrap().clear();
+    std::thread::spawn(move || {
+        for path in paths {
+            let size = dir_size(&path);
+            if SIZE_SCAN.load(Ordering::SeqCst) != scan {
+                return;
+            }
+            PREFIX_SIZES.lock().unwrap().insert(path, size);
+        }
+    });
+}
+
+// Size of a scanned prefix, or None while it's still being measured
+pub fn prefix_size(pfx: &Path) -> Option<u64> {
+    PREFIX_SIZES.lock().unwrap().get(pfx).copied()
+}
+
+fn prefix_info(name: String, path: PathBuf) -> PrefixInfo {
+    PrefixInfo {
+        version: prefix_version(&path),
+        name,
+        path,
+    }
+}
+
+// Proton writes "<timestamp> <version>" to the prefix's version file; Wine prefixes have none
+fn prefix_version(pfx: &Path) -> String {
+    if !prefix_initialized(pfx) {
+        return "Not initialized".to_string();
+    }
+    std::fs::read_to_string(pfx.join("version"))
+        .ok()
+        .and_then(|v| v.split_whitespace().nth(1).map(|s| s.to_string()))
+        .unwrap_or("Wine".to_string())
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+// Erases a prefix so it's rebuilt from the template the next time it's used
+pub fn reset_prefix(pfx: &Path) -> Result<(), Box<dyn Error>> {
+    if pfx.exists() {
+        std::fs::remove_dir_all(pfx)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use crate::paths::*;
+
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Names umu-run resolves on its own, downloading the build if needed
+pub static UMU_PROTON_NAMES: [&str; 2] = ["GE-Proton", "UMU-Proton"];
+
+#[derive(Clone)]
+pub struct ProtonBuild {
+    pub name: String,
+    pub version: String,
+    pub path: PathBuf,
+}
+
+impl ProtonBuild {
+    fn from_dir(path: &Path) -> Option<Self> {
+        if !path.join("proton").exists() {
+            return None;
+        }
+        let name = path.file_name()?.to_string_lossy().to_string();
+        // The version file reads "<timestamp> <version>"
+        let version = std::fs::read_to_string(path.join("version"))
+            .ok()
+            .and_then(|v| v.split_whitespace().nth(1).map(|s| s.to_string()))
+            .unwrap_or_default();
+        Some(ProtonBuild {
+            name,
+            version,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn display(&self) -> String {
+        if self.version.is_empty() || self.version == self.name {
+            self.name.clone()
+        } else {
+            format!("{} ({})", self.name, self.version)
+        }
+    }
+}
+
+fn proton_search_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![
+        PATH_STEAM.join("compatibilitytools.d"),
+        // umu-run installs the builds it downloads here
+        PATH_LOCAL_SHARE.join("Steam/compatibilitytools.d"),
+        PATH_CACHE.join("umu"),
+    ];
+
+    let libraries = steamlocate::SteamDir::locate()
+        .and_then(|steamdir| steamdir.library_paths())
+        .unwrap_or_else(|_| vec![PATH_STEAM.clone()]);
+    for library in libraries {
+        dirs.push(library.join("steamapps/common"));
+    }
+    dirs
+}
+
+// Finds every installed Proton build in Steam's compatibilitytools.d, Steam libraries and umu's cache
+pub fn scan_proton_builds() -> Vec<ProtonBuild> {
+    let mut out: Vec<ProtonBuild> = Vec::new();
+
+    for dir in proton_search_dirs() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            if let Some(build) = ProtonBuild::from_dir(&path) {
+                if !out
+                    .iter()
+                    .any(|b| b.path == build.path || b.name == build.name)
+                {
+                    out.push(build);
+                }
+            }
+        }
+    }
+
+    out.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    out
+}
+
+// Turns a Proton setting into a PROTONPATH value for umu-run, erroring if it can't be found.
+// Empty defaults to GE-Proton; otherwise this is a name umu resolves itself, a path, or an installed build's name.
+pub fn resolve_proton(version: &str) -> Result<String, Box<dyn Error>> {
+    if version.is_empty() {
+        return Ok("GE-Proton".to_string());
+    }
+    if UMU_PROTON_NAMES.contains(&version) {
+        return Ok(version.to_string());
+    }
+
+    let path = PathBuf::from(version);
+    if path.is_absolute() {
+        if path.join("proton").exists() {
+            return Ok(version.to_string());
+        }
+        return Err(format!("No Proton build found at {version}").into());
+    }
+
+    if let Some(build) = scan_proton_builds().into_iter().find(|b| b.name == version) {
+        return Ok(build.path.to_string_lossy().to_string());
+    }
+
+    Err(format!("Proton version \"{version}\" is not installed").into())
+}
@@ -0,0 +1,233 @@
+use evdev::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+// Buttons and axes remaps can be made for, with the names shown for them
+pub static GAMEPAD_BUTTONS: [(KeyCode, &str); 17] = [
+    (KeyCode::BTN_SOUTH, "South (A)"),
+    (KeyCode::BTN_EAST, "East (B)"),
+    (KeyCode::BTN_NORTH, "North (Y)"),
+    (KeyCode::BTN_WEST, "West (X)"),
+    (KeyCode::BTN_TL, "Left Bumper"),
+    (KeyCode::BTN_TR, "Right Bumper"),
+    (KeyCode::BTN_TL2, "Left Trigger"),
+    (KeyCode::BTN_TR2, "Right Trigger"),
+    (KeyCode::BTN_SELECT, "Select"),
+    (KeyCode::BTN_START, "Start"),
+    (KeyCode::BTN_MODE, "Guide"),
+    (KeyCode::BTN_THUMBL, "Left Stick Click"),
+    (KeyCode::BTN_THUMBR, "Right Stick Click"),
+    (KeyCode::BTN_DPAD_UP, "D-pad Up"),
+    (KeyCode::BTN_DPAD_DOWN, "D-pad Down"),
+    (KeyCode::BTN_DPAD_LEFT, "D-pad Left"),
+    (KeyCode::BTN_DPAD_RIGHT, "D-pad Right"),
+];
+
+pub static GAMEPAD_AXES: [(AbsoluteAxisCode, &str); 8] = [
+    (AbsoluteAxisCode::ABS_X, "Left Stick X"),
+    (AbsoluteAxisCode::ABS_Y, "Left Stick Y"),
+    (AbsoluteAxisCode::ABS_RX, "Right Stick X"),
+    (AbsoluteAxisCode::ABS_RY, "Right Stick Y"),
+    (AbsoluteAxisCode::ABS_Z, "Left Trigger Axis"),
+    (AbsoluteAxisCode::ABS_RZ, "Right Trigger Axis"),
+    (AbsoluteAxisCode::ABS_HAT0X, "D-pad X"),
+    (AbsoluteAxisCode::ABS_HAT0Y, "D-pad Y"),
+];
+
+// The stick axes radial deadzones apply to, as the game sees them
+const LEFT_STICK: (AbsoluteAxisCode, AbsoluteAxisCode) =
+    (AbsoluteAxisCode::ABS_X, AbsoluteAxisCode::ABS_Y);
+const RIGHT_STICK: (AbsoluteAxisCode, AbsoluteAxisCode) =
+    (AbsoluteAxisCode::ABS_RX, AbsoluteAxisCode::ABS_RY);
+
+// A named set of changes made to a controller's input before the game sees it. Buttons and
+// axes are stored by their evdev names, e.g. "BTN_SOUTH" or "ABS_RX".
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RemapProfile {
+    pub name: String,
+    // Button pressed -> button the game sees
+    #[serde(default)]
+    pub buttons: BTreeMap<String, String>,
+    // Axis moved -> axis the game sees
+    #[serde(default)]
+    pub axes: BTreeMap<String, String>,
+    // Axes whose direction is flipped, by the axis moved
+    #[serde(default)]
+    pub inverted: Vec<String>,
+    // Radial deadzones for each stick, as a fraction of its range
+    #[serde(default)]
+    pub left_deadzone: f32,
+    #[serde(default)]
+    pub right_deadzone: f32,
+}
+
+pub fn button_name(code: KeyCode) -> String {
+    format!("{code:?}")
+}
+
+pub fn axis_name(code: AbsoluteAxisCode) -> String {
+    format!("{code:?}")
+}
+
+// A remap profile resolved against the controller it applies to
+pub struct Remapper {
+    buttons: HashMap<KeyCode, KeyCode>,
+    axes: HashMap<AbsoluteAxisCode, AbsoluteAxisCode>,
+    inverted: Vec<AbsoluteAxisCode>,
+    sticks: Vec<((AbsoluteAxisCode, AbsoluteAxisCode), f32)>,
+    // Range of each axis, including the ones only the remap sends
+    ranges: HashMap<AbsoluteAxisCode, (i32, i32)>,
+    // Axes the remap sends that the controller doesn't have, which take the range of the
+    // axis mapped to them
+    extra_axes: Vec<(AbsoluteAxisCode, (i32, i32))>,
+    // Latest value of each stick axis, as the game sees it
+    stick_values: HashMap<AbsoluteAxisCode, i32>,
+}
+
+impl Remapper {
+    pub fn new(profile: RemapProfile, dev: &Device) -> Self {
+        let buttons = profile
+            .buttons
+            .iter()
+            .filter_map(|(from, to)| {
+                Some((KeyCode::from_str(from).ok()?, KeyCode::from_str(to).ok()?))
+            })
+            .collect();
+        let axes: HashMap<AbsoluteAxisCode, AbsoluteAxisCode> = profile
+            .axes
+            .iter()
+            .filter_map(|(from, to)| {
+                Some((
+                    AbsoluteAxisCode::from_str(from).ok()?,
+                    AbsoluteAxisCode::from_str(to).ok()?,
+                ))
+            })
+            .collect();
+        let inverted = profile
+            .inverted
+            .iter()
+            .filter_map(|axis| AbsoluteAxisCode::from_str(axis).ok())
+            .collect();
+        let sticks = [
+            (LEFT_STICK, profile.left_deadzone),
+            (RIGHT_STICK, profile.right_deadzone),
+        ]
+        .into_iter()
+        .filter(|(_, deadzone)| *deadzone > 0.0)
+        .collect();
+        let mut ranges: HashMap<AbsoluteAxisCode, (i32, i32)> = dev
+            .get_absinfo()
+            .map(|axes| {
+                axes.map(|(code, info)| (code, (info.minimum(), info.maximum())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let extra_axes: Vec<_> = axes
+            .iter()
+            .filter(|(_, to)| !ranges.contains_key(to))
+            .filter_map(|(from, to)| Some((*to, *ranges.get(from)?)))
+            .collect();
+        ranges.extend(extra_axes.iter().copied());
+
+        Remapper {
+            buttons,
+            axes,
+            inverted,
+            sticks,
+            ranges,
+            extra_axes,
+            stick_values: HashMap::new(),
+        }
+    }
+
+    // Buttons the remap sends, which the virtual pad needs even if the controller lacks them
+    pub fn extra_buttons(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.buttons.values().copied()
+    }
+
+    pub fn extra_axes(&self) -> impl Iterator<Item = (AbsoluteAxisCode, (i32, i32))> + '_ {
+        self.extra_axes.iter().copied()
+    }
+
+    // Maps one frame of the controller's events to what the game sees
+    pub fn apply(&mut self, frame: &[InputEvent]) -> Vec<InputEvent> {
+        let mut out = Vec::with_capacity(frame.len());
+        let mut sticks_moved = Vec::new();
+        for event in frame {
+            match event.destructure() {
+                EventSummary::Key(_, code, value) => {
+                    let code = self.buttons.get(&code).copied().unwrap_or(code);
+                    out.push(*KeyEvent::new(code, value));
+                }
+                EventSummary::AbsoluteAxis(_, code, value) => {
+                    let Some(&(min, max)) = self.ranges.get(&code) else {
+                        out.push(*event);
+                        continue;
+                    };
+                    let mut value = value;
+                    if self.inverted.contains(&code) {
+                        value = min + max - value;
+                    }
+                    let to = self.axes.get(&code).copied().unwrap_or(code);
+                    if let Some(&(to_min, to_max)) = self.ranges.get(&to) {
+                        value = rescale(value, (min, max), (to_min, to_max));
+                    }
+
+                    if self.sticks.iter().any(|((x, y), _)| *x == to || *y == to) {
+                        self.stick_values.insert(to, value);
+                        sticks_moved.push(to);
+                    } else {
+                        out.push(*AbsoluteAxisEvent::new(to, value));
+                    }
+                }
+                _ => out.push(*event),
+            }
+        }
+
+        for ((x, y), deadzone) in &self.sticks {
+            if sticks_moved.contains(x) || sticks_moved.contains(y) {
+                let (vx, vy) = self.apply_deadzone((*x, *y), *deadzone);
+                out.push(*AbsoluteAxisEvent::new(*x, vx));
+                out.push(*AbsoluteAxisEvent::new(*y, vy));
+            }
+        }
+        out
+    }
+
+    // Scales the stick's position so the deadzone's edge becomes its center
+    fn apply_deadzone(
+        &self,
+        (x, y): (AbsoluteAxisCode, AbsoluteAxisCode),
+        deadzone: f32,
+    ) -> (i32, i32) {
+        let range = |axis| self.ranges.get(&axis).copied().unwrap_or((-1, 1));
+        let normalize = |axis| {
+            let (min, max) = range(axis);
+            let center = (min + max) as f32 / 2.0;
+            let value = self.stick_values.get(&axis).map_or(center, |v| *v as f32);
+            (value - center) / ((max - min) as f32 / 2.0)
+        };
+        let denormalize = |axis, n: f32| {
+            let (min, max) = range(axis);
+            let center = (min + max) as f32 / 2.0;
+            (center + n.clamp(-1.0, 1.0) * (max - min) as f32 / 2.0).round() as i32
+        };
+
+        let (nx, ny) = (normalize(x), normalize(y));
+        let magnitude = (nx * nx + ny * ny).sqrt();
+        let scale = match magnitude {
+            m if m <= deadzone => 0.0,
+            m => (m - deadzone) / (1.0 - deadzone) / m,
+        };
+        (denormalize(x, nx * scale), denormalize(y, ny * scale))
+    }
+}
+
+fn rescale(value: i32, (min, max): (i32, i32), (to_min, to_max): (i32, i32)) -> i32 {
+    if max == min {
+        return to_min;
+    }
+    let fraction = (value - min) as f64 / (max - min) as f64;
+    (to_min as f64 + fraction * (to_max - to_min) as f64).round() as i32
+}
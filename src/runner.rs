@@ -0,0 +1,256 @@
+use crate::app::PartyConfig;
+use crate::game::Game::{self, ExecRef, HandlerRef};
+use crate::paths::*;
+use crate::proton::resolve_proton;
+use crate::util::*;
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// DLLs provided by DXVK, overridden to native when it's installed into a prefix
+static DXVK_DLLS: [&str; 5] = ["d3d8", "d3d9", "d3d10core", "d3d11", "dxgi"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum RunnerType {
+    #[default]
+    Umu,
+    Wine,
+}
+
+impl RunnerType {
+    pub fn display(&self) -> &'static str {
+        match self {
+            RunnerType::Umu => "Proton (umu)",
+            RunnerType::Wine => "Wine",
+        }
+    }
+}
+
+// How Windows games are run
+pub enum Runner {
+    Umu { protonpath: String },
+    Wine { wine: String, dxvk: Option<PathBuf> },
+}
+
+impl Runner {
+    pub fn new(game: &Game, cfg: &PartyConfig) -> Result<Self, Box<dyn Error>> {
+        let (runner_type, proton_version) = match game {
+            HandlerRef(h) => (cfg.runner_for(&h.uid), cfg.proton_version_for(&h.uid)),
+            ExecRef(_) => (cfg.runner, cfg.proton_version.as_str()),
+        };
+        match runner_type {
+            RunnerType::Umu => Ok(Runner::Umu {
+                protonpath: resolve_proton(proton_version)?,
+            }),
+            RunnerType::Wine => Ok(Runner::Wine {
+                wine: resolve_wine(&cfg.wine_path)?,
+                dxvk: match cfg.wine_dxvk {
+                    true => Some(resolve_dxvk(&cfg.dxvk_path)?),
+                    false => None,
+                },
+            }),
+        }
+    }
+
+    // Environment variables the runner needs, in "KEY=value " form
+    pub fn env(&self) -> String {
+        match self {
+            Runner::Umu { protonpath } => {
+                format!("PROTON_VERB=run PROTONPATH=\"{protonpath}\" ")
+            }
+            Runner::Wine { wine, .. } => format!("WINE=\"{wine}\" "),
+        }
+    }
+
+    // DLL overrides the runner adds on top of the handler's
+    pub fn dll_overrides(&self) -> Vec<(String, String)> {
+        match self {
+            Runner::Wine { dxvk: Some(_), .. } => DXVK_DLLS
+                .iter()
+                .map(|d| (d.to_string(), "n".to_string()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // The program the game executable is passed to
+    pub fn command(&self) -> String {
+        match self {
+            Runner::Umu { .. } => format!("{}", BIN_UMU_RUN.to_string_lossy()),
+            Runner::Wine { wine, .. } => format!("\"{wine}\""),
+        }
+    }
+
+    // Folders outside the prefix the runner reads from, for sandboxes that hide home
+    pub fn sandbox_paths(&self) -> Vec<String> {
+        let path = match self {
+            Runner::Umu { protonpath } => PathBuf::from(protonpath),
+            // A Wine build's bin folder sits next to its lib and share folders
+            Runner::Wine { wine, .. } => PathBuf::from(wine)
+                .parent()
+                .and_then(|bin| bin.parent())
+                .map(|p| p.to_path_buf())
+                .unwrap_or_default(),
+        };
+        match path.is_absolute() {
+            true => vec![path.display().to_string()],
+            false => Vec::new(),
+        }
+    }
+
+    // Name of the Windows user whose AppData/Documents live in the prefix
+    pub fn wine_user(&self) -> String {
+        match self {
+            Runner::Umu { .. } => "steamuser".to_string(),
+            Runner::Wine { .. } => std::env::var("USER").unwrap_or("steamuser".to_string()),
+        }
+    }
+
+    // Identifies the Proton build or Wine binary, to tell which one a prefix was made with
+    pub fn id(&self) -> String {
+        match self {
+            Runner::Umu { protonpath } => protonpath.clone(),
+            Runner::Wine { wine, .. } => wine.clone(),
+        }
+    }
+
+    // Creates and boots a new prefix
+    pub fn init_prefix(&self, pfx: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(pfx)?;
+        println!("Initializing prefix {}", pfx.display());
+
+        let status = match self {
+            // umu-run sets up the prefix and exits when given an empty executable
+            Runner::Umu { protonpath } => std::process::Command::new(&*BIN_UMU_RUN)
+                .arg("")
+                .env("WINEPREFIX", pfx)
+                .env("PROTONPATH", protonpath)
+                .env("GAMEID", "umu-default")
+                .status()?,
+            Runner::Wine { wine, .. } => std::process::Command::new(wine)
+                .arg("wineboot")
+                .arg("-i")
+                .env("WINEPREFIX", pfx)
+                .status()?,
+        };
+        if !status.success() {
+            return Err(format!("Failed to initialize prefix {}", pfx.display()).into());
+        }
+        Ok(())
+    }
+
+    // Runs a Windows program in a prefix and waits for it to finish
+    pub fn run_in_prefix(
+        &self,
+        pfx: &Path,
+        program: &str,
+        args: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        let status = match self {
+            Runner::Umu { protonpath } => std::process::Command::new(&*BIN_UMU_RUN)
+                .arg(program)
+                .args(args)
+                .env("WINEPREFIX", pfx)
+                .env("PROTONPATH", protonpath)
+                .env("GAMEID", "umu-default")
+                .status()?,
+            Runner::Wine { wine, .. } => std::process::Command::new(wine)
+                .arg(program)
+                .args(args)
+                .env("WINEPREFIX", pfx)
+                .status()?,
+        };
+        if !status.success() {
+            return Err(format!("{program} failed in prefix {}", pfx.display()).into());
+        }
+        Ok(())
+    }
+
+    // umu-run provides winetricks itself; plain Wine needs winetricks on PATH
+    pub fn winetricks(&self, pfx: &Path, verb: &str) -> Result<(), Box<dyn Error>> {
+        let args = ["-q".to_string(), verb.to_string()];
+        let Runner::Wine { wine, .. } = self else {
+            return self.run_in_prefix(pfx, "winetricks", &args);
+        };
+        let winetricks =
+            find_in_path("winetricks").ok_or_else(|| "winetricks not found in PATH")?;
+        let status = std::process::Command::new(winetricks)
+            .args(args)
+            .env("WINE", wine)
+            .env("WINEPREFIX", pfx)
+            .status()?;
+        if !status.success() {
+            return Err(format!("winetricks {verb} failed in prefix {}", pfx.display()).into());
+        }
+        Ok(())
+    }
+
+    // Installs anything the runner needs on top of an initialized prefix
+    pub fn setup_prefix(&self, pfx: &Path) -> Result<(), Box<dyn Error>> {
+        if let Runner::Wine {
+            dxvk: Some(dxvk), ..
+        } = self
+        {
+            install_dxvk(pfx, dxvk)?;
+        }
+        Ok(())
+    }
+}
+
+// Empty uses the wine on PATH
+fn resolve_wine(wine_path: &str) -> Result<String, Box<dyn Error>> {
+    if wine_path.is_empty() {
+        return find_in_path("wine")
+            .map(|p| p.to_string_lossy().to_string())
+            .ok_or_else(|| "Wine not found in PATH".into());
+    }
+    let path = expand_home(wine_path);
+    if !path.is_file() {
+        return Err(format!("Wine binary not found: {wine_path}").into());
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn resolve_dxvk(dxvk_path: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let path = expand_home(dxvk_path);
+    if dxvk_path.is_empty() || !path.join("x64").exists() {
+        return Err("DXVK folder not found; it should contain x64 and x32 folders".into());
+    }
+    Ok(path)
+}
+
+// Paths typed into the settings may start with ~, which only a shell would expand
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => PATH_HOME.join(rest),
+        None if path == "~" => PATH_HOME.clone(),
+        None => PathBuf::from(path),
+    }
+}
+
+// Copies DXVK's DLLs into the prefix, skipping it if this DXVK is already installed
+fn install_dxvk(pfx: &Path, dxvk: &Path) -> Result<(), Box<dyn Error>> {
+    let marker = pfx.join(".partydeck_dxvk");
+    let installed = std::fs::read_to_string(&marker).unwrap_or_default();
+    if installed == dxvk.to_string_lossy() {
+        return Ok(());
+    }
+
+    println!("Installing DXVK into {}", pfx.display());
+    for (arch, dest) in [("x64", "system32"), ("x32", "syswow64")] {
+        let src = dxvk.join(arch);
+        let dest = pfx.join("drive_c/windows").join(dest);
+        if !src.exists() || !dest.exists() {
+            continue;
+        }
+        for dll in DXVK_DLLS {
+            let file = format!("{dll}.dll");
+            if src.join(&file).exists() {
+                std::fs::copy(src.join(&file), dest.join(&file))?;
+            }
+        }
+    }
+    std::fs::write(marker, dxvk.to_string_lossy().as_bytes())?;
+    Ok(())
+}
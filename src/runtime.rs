@@ -0,0 +1,149 @@
+use crate::handler::Handler;
+use crate::paths::*;
+
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SteamRuntime {
+    Scout,
+    Soldier,
+    Sniper,
+}
+
+static ALL_RUNTIMES: [SteamRuntime; 3] = [
+    SteamRuntime::Scout,
+    SteamRuntime::Soldier,
+    SteamRuntime::Sniper,
+];
+
+impl SteamRuntime {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "scout" => Some(SteamRuntime::Scout),
+            "soldier" => Some(SteamRuntime::Soldier),
+            "sniper" => Some(SteamRuntime::Sniper),
+            _ => None,
+        }
+    }
+
+    // Maps the compat tool names Steam stores in its config to runtimes
+    fn from_compat_tool(tool: &str) -> Option<Self> {
+        match tool {
+            "steamlinuxruntime" => Some(SteamRuntime::Scout),
+            "steamlinuxruntime_soldier" => Some(SteamRuntime::Soldier),
+            "steamlinuxruntime_sniper" => Some(SteamRuntime::Sniper),
+            _ => None,
+        }
+    }
+
+    pub fn display(&self) -> &'static str {
+        match self {
+            SteamRuntime::Scout => "Steam Runtime 1.0 (scout)",
+            SteamRuntime::Soldier => "Steam Runtime 2.0 (soldier)",
+            SteamRuntime::Sniper => "Steam Runtime 3.0 (sniper)",
+        }
+    }
+
+    // Finds the script games are run through, searching every Steam library for soldier and sniper
+    pub fn entry_point(&self) -> Option<PathBuf> {
+        let dir = match self {
+            SteamRuntime::Scout => {
+                let run = PATH_STEAM.join("ubuntu12_32/steam-runtime/run.sh");
+                return run.exists().then_some(run);
+            }
+            SteamRuntime::Soldier => "SteamLinuxRuntime_soldier",
+            SteamRuntime::Sniper => "SteamLinuxRuntime_sniper",
+        };
+
+        let libraries = steamlocate::SteamDir::locate()
+            .and_then(|steamdir| steamdir.library_paths())
+            .unwrap_or_else(|_| vec![PATH_STEAM.clone()]);
+        libraries
+            .into_iter()
+            .map(|library| {
+                library
+                    .join("steamapps/common")
+                    .join(dir)
+                    .join("_v2-entry-point")
+            })
+            .find(|path| path.exists())
+    }
+}
+
+pub fn installed_runtimes() -> Vec<(SteamRuntime, PathBuf)> {
+    ALL_RUNTIMES
+        .iter()
+        .filter_map(|rt| rt.entry_point().map(|path| (*rt, path)))
+        .collect()
+}
+
+// Describes which runtimes are installed, for error messages and the game page
+pub fn runtimes_summary() -> String {
+    let installed = installed_runtimes();
+    if installed.is_empty() {
+        return "No Steam runtimes are installed.".to_string();
+    }
+    let names: Vec<&str> = installed.iter().map(|(rt, _)| rt.display()).collect();
+    format!("Installed runtimes: {}", names.join(", "))
+}
+
+// How the handler's runtime is shown on the game page, naming what "auto" resolves to
+pub fn runtime_label(h: &Handler) -> String {
+    match h.runtime.as_str() {
+        "auto" => match steam_configured_runtime(h) {
+            Some(rt) => format!("auto: {}", rt.display()),
+            None => format!(
+                "auto: {} (no runtime set in Steam)",
+                SteamRuntime::Scout.display()
+            ),
+        },
+        name => name.to_string(),
+    }
+}
+
+// Picks the runtime for a native handler. "auto" follows the compat tool the user
+// set for the game in Steam, falling back to scout like Steam does.
+pub fn resolve_runtime(h: &Handler) -> Result<Option<(SteamRuntime, PathBuf)>, Box<dyn Error>> {
+    let runtime = match h.runtime.as_str() {
+        "" => return Ok(None),
+        "auto" => match steam_configured_runtime(h) {
+            Some(rt) => rt,
+            None => {
+                println!(
+                    "No runtime set in Steam for {}, falling back to scout",
+                    h.uid
+                );
+                match SteamRuntime::Scout.entry_point() {
+                    Some(path) => return Ok(Some((SteamRuntime::Scout, path))),
+                    None => return Ok(None),
+                }
+            }
+        },
+        name => SteamRuntime::from_name(name)
+            .ok_or_else(|| format!("Unknown runtime \"{name}\" in handler {}", h.uid))?,
+    };
+
+    match runtime.entry_point() {
+        Some(path) => {
+            println!("Using {} for {}", runtime.display(), h.uid);
+            Ok(Some((runtime, path)))
+        }
+        None => Err(format!(
+            "{} not found. Install it through Steam.\n\n{}",
+            runtime.display(),
+            runtimes_summary()
+        )
+        .into()),
+    }
+}
+
+fn steam_configured_runtime(h: &Handler) -> Option<SteamRuntime> {
+    let appid = h.steam_appid.as_ref()?.parse::<u32>().ok()?;
+    let mapping = steamlocate::SteamDir::locate()
+        .ok()?
+        .compat_tool_mapping()
+        .ok()?;
+    let tool = mapping.get(&appid)?.name.as_ref()?;
+    SteamRuntime::from_compat_tool(tool)
+}
@@ -0,0 +1,206 @@
+use crate::paths::*;
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::LazyLock;
+
+// Installed bubblewrap's (major, minor) version, checked once
+static BWRAP_VERSION: LazyLock<Option<(u32, u32)>> = LazyLock::new(|| {
+    let output = Command::new("bwrap").arg("--version").output().ok()?;
+    // Prints e.g. "bubblewrap 0.10.0"
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut parts = text.split_whitespace().last()?.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+});
+
+// --overlay-src and --overlay were added in bubblewrap 0.10
+pub fn check_bwrap_overlay() -> Result<(), String> {
+    match *BWRAP_VERSION {
+        Some(version) if version >= (0, 10) => Ok(()),
+        Some((major, minor)) => Err(format!(
+            "This game's handler uses overlay mode, which needs bubblewrap 0.10 or newer, but bubblewrap {major}.{minor} is installed. Update bubblewrap to play it."
+        )),
+        None => Err("This game's handler uses overlay mode, which needs bubblewrap 0.10 or newer, but PartyDeck couldn't tell which version is installed.".to_string()),
+    }
+}
+
+// How much of the host a game's bwrap sandbox can see and write to
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum SandboxPolicy {
+    // The whole host is writable
+    #[default]
+    Permissive,
+    // The host is read-only, apart from the game, PartyDeck's data and the caches games need
+    ReadOnlyHost,
+    // Like ReadOnlyHost, but home is empty apart from the paths the game needs
+    PrivateHome,
+}
+
+impl SandboxPolicy {
+    pub fn display(&self) -> &'static str {
+        match self {
+            SandboxPolicy::Permissive => "Permissive",
+            SandboxPolicy::ReadOnlyHost => "Read-only host",
+            SandboxPolicy::PrivateHome => "Private home",
+        }
+    }
+
+    // Names handlers use for "game.sandbox"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "permissive" => Some(SandboxPolicy::Permissive),
+            "readonly" => Some(SandboxPolicy::ReadOnlyHost),
+            "private_home" => Some(SandboxPolicy::PrivateHome),
+            _ => None,
+        }
+    }
+}
+
+pub static ALL_POLICIES: [SandboxPolicy; 3] = [
+    SandboxPolicy::Permissive,
+    SandboxPolicy::ReadOnlyHost,
+    SandboxPolicy::PrivateHome,
+];
+
+// A bwrap mount. The "try" variants are skipped by bwrap if the source doesn't exist.
+pub enum Mount {
+    DevBind(String, String),
+    Bind(String, String),
+    BindTry(String, String),
+    RoBind(String, String),
+    RoBindTry(String, String),
+    Tmpfs(String),
+    Proc(String),
+    Overlay {
+        lower: Vec<String>,
+        upper: String,
+        work: String,
+        dest: String,
+    },
+}
+
+pub struct Sandbox {
+    mounts: Vec<Mount>,
+    chdir: Option<String>,
+}
+
+impl Sandbox {
+    // Starts a sandbox with the policy's view of the host. writable and readable are the
+    // paths the game needs, which stricter policies expose on top of their base mounts.
+    pub fn new(policy: SandboxPolicy, writable: &[String], readable: &[String]) -> Self {
+        let mut mounts = Vec::new();
+        if policy == SandboxPolicy::Permissive {
+            mounts.push(Mount::DevBind("/".to_string(), "/".to_string()));
+            mounts.push(Mount::Tmpfs("/tmp".to_string()));
+            return Sandbox {
+                mounts,
+                chdir: None,
+            };
+        }
+
+        mounts.push(Mount::RoBind("/".to_string(), "/".to_string()));
+        mounts.push(Mount::DevBind("/dev".to_string(), "/dev".to_string()));
+        mounts.push(Mount::Proc("/proc".to_string()));
+        mounts.push(Mount::Tmpfs("/tmp".to_string()));
+        if policy == SandboxPolicy::PrivateHome {
+            mounts.push(Mount::Tmpfs(PATH_HOME.display().to_string()));
+            for path in readable {
+                mounts.push(Mount::RoBindTry(path.clone(), path.clone()));
+            }
+        }
+
+        // Audio and display sockets live in the runtime dir
+        if let Ok(runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
+            mounts.push(Mount::BindTry(runtime_dir.clone(), runtime_dir));
+        }
+        for path in writable {
+            mounts.push(Mount::BindTry(path.clone(), path.clone()));
+        }
+
+        Sandbox {
+            mounts,
+            chdir: None,
+        }
+    }
+
+    pub fn mount(&mut self, mount: Mount) {
+        self.mounts.push(mount);
+    }
+
+    pub fn bind(&mut self, src: &str, dest: &str) {
+        self.mounts.push(Mount::Bind(src.to_string(), dest.to_string()));
+    }
+
+    pub fn chdir(&mut self, dir: &str) {
+        self.chdir = Some(dir.to_string());
+    }
+
+    // The bwrap command line, without the command to run
+    pub fn to_args(&self) -> String {
+        let mut out = String::from("bwrap --die-with-parent ");
+        for mount in &self.mounts {
+            match mount {
+                Mount::DevBind(src, dest) => {
+                    out.push_str(&format!("--dev-bind \"{src}\" \"{dest}\" "))
+                }
+                Mount::Bind(src, dest) => out.push_str(&format!("--bind \"{src}\" \"{dest}\" ")),
+                Mount::BindTry(src, dest) => {
+                    out.push_str(&format!("--bind-try \"{src}\" \"{dest}\" "))
+                }
+                Mount::RoBind(src, dest) => {
+                    out.push_str(&format!("--ro-bind \"{src}\" \"{dest}\" "))
+                }
+                Mount::RoBindTry(src, dest) => {
+                    out.push_str(&format!("--ro-bind-try \"{src}\" \"{dest}\" "))
+                }
+                Mount::Tmpfs(dest) => out.push_str(&format!("--tmpfs \"{dest}\" ")),
+                Mount::Proc(dest) => out.push_str(&format!("--proc \"{dest}\" ")),
+                // Lower layers are listed bottom to top
+                Mount::Overlay {
+                    lower,
+                    upper,
+                    work,
+                    dest,
+                } => {
+                    for src in lower {
+                        out.push_str(&format!("--overlay-src \"{src}\" "));
+                    }
+                    out.push_str(&format!("--overlay \"{upper}\" \"{work}\" \"{dest}\" "));
+                }
+            }
+        }
+        if let Some(dir) = &self.chdir {
+            out.push_str(&format!("--chdir \"{dir}\" "));
+        }
+        out
+    }
+}
+
+// Paths under home that stricter policies need writable: PartyDeck's data, umu's Proton
+// downloads and the caches shaders and runtimes are kept in
+pub fn default_writable_paths() -> Vec<String> {
+    [
+        PATH_PARTY.clone(),
+        PATH_LOCAL_SHARE.join("umu"),
+        PATH_LOCAL_SHARE.join("Steam/compatibilitytools.d"),
+        PATH_CACHE.clone(),
+    ]
+    .iter()
+    .map(|p| p.display().to_string())
+    .collect()
+}
+
+// Paths a private home still needs to read: Steam, its libraries and PartyDeck's own binaries
+pub fn default_readable_paths() -> Vec<String> {
+    let mut paths: Vec<PathBuf> = vec![PATH_STEAM.clone(), PATH_RES.clone()];
+    if let Some(bin) = BIN_UMU_RUN.parent() {
+        paths.push(bin.to_path_buf());
+    }
+    if let Ok(libraries) = steamlocate::SteamDir::locate().and_then(|s| s.library_paths()) {
+        paths.extend(libraries);
+    }
+    paths.iter().map(|p| p.display().to_string()).collect()
+}
@@ -0,0 +1,446 @@
+use crate::app::{PartyConfig, load_cfg};
+use crate::game::{Executable, Game};
+use crate::handler::{Handler, mirror_instance_dir, scan_handlers};
+use crate::input::DeviceInfo;
+use crate::instance::Instance;
+use crate::netns::NetNamespace;
+use crate::paths::*;
+use crate::util::*;
+use crate::vpad::VirtualPad;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// How long each stage of the teardown waits before escalating to the next signal
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+// How long Start + Select must be held on a player's gamepad to end the session
+const COMBO_HOLD: Duration = Duration::from_secs(2);
+
+pub struct Session {
+    pub game: Game,
+    pub cfg: PartyConfig,
+    pub instances: Vec<Instance>,
+    // Snapshot of the devices at launch; instance device indices point into this
+    pub input_devices: Vec<DeviceInfo>,
+    pub gamepads: Vec<String>,
+    // Process group IDs of each instance; children is empty for a reattached session
+    pub pgids: Vec<u32>,
+    pub children: Vec<Child>,
+    pub kwin_script: Option<String>,
+    pub netns: Option<NetNamespace>,
+    // Removed along with the session; a reattached session has none
+    pub vpads: Vec<VirtualPad>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum LockGame {
+    Handler(String),
+    Executable(PathBuf, String),
+}
+
+// Written to session.json while a session runs, so a crashed PartyDeck can be recovered from
+#[derive(Serialize, Deserialize)]
+pub struct SessionLock {
+    partydeck_pid: u32,
+    game_name: String,
+    game: LockGame,
+    instances: Vec<Instance>,
+    input_devices: Vec<DeviceInfo>,
+    gamepads: Vec<String>,
+    pgids: Vec<u32>,
+    // Start time of each pgid's process and the boot they were written in, so processes that
+    // reused the numbers after a reboot or PID wraparound are never mistaken for instances
+    #[serde(default)]
+    starttimes: Vec<u64>,
+    #[serde(default)]
+    boot_id: String,
+    kwin_script: Option<String>,
+    #[serde(default)]
+    netns: Option<NetNamespace>,
+    // Instances read virtual pads, whose nodes go away with the PartyDeck that made them
+    #[serde(default)]
+    virtual_pads: bool,
+}
+
+impl SessionLock {
+    pub fn game_name(&self) -> &str {
+        &self.game_name
+    }
+
+    // Whether the PartyDeck that wrote this lockfile is still running
+    pub fn owner_alive(&self) -> bool {
+        pid_alive(self.partydeck_pid)
+            && std::fs::read_to_string(format!("/proc/{}/comm", self.partydeck_pid))
+                .is_ok_and(|comm| comm.trim() == "partydeck")
+    }
+
+    // Reattaching can't bring back the virtual pads the instances were reading
+    pub fn can_reattach(&self) -> bool {
+        !self.virtual_pads
+    }
+
+    pub fn instances_alive(&self) -> bool {
+        self.verified_pgids()
+            .into_iter()
+            .any(|pgid| process_tree(pgid).into_iter().any(pid_alive))
+    }
+
+    // The pgids that still belong to this session's instances. Any that can't be verified are
+    // treated as already dead.
+    fn verified_pgids(&self) -> Vec<u32> {
+        if self.boot_id.is_empty() || self.boot_id != boot_id() {
+            return Vec::new();
+        }
+        self.pgids
+            .iter()
+            .zip(&self.starttimes)
+            .filter(|(pgid, start)| process_starttime(**pgid) == Some(**start))
+            .map(|(pgid, _)| *pgid)
+            .collect()
+    }
+
+    // The session's network namespace, if its holder is still the process that was started
+    fn verified_netns(&self) -> Option<NetNamespace> {
+        if self.boot_id.is_empty() || self.boot_id != boot_id() {
+            return None;
+        }
+        self.netns.filter(|netns| netns.holder_alive())
+    }
+}
+
+// What the GUI needs to know about the running session
+pub struct SessionInfo {
+    pub game_name: String,
+    pub is_handler: bool,
+    pub players: Vec<String>,
+    // The devices the instances are using
+    pub devices: Vec<DeviceInfo>,
+}
+
+pub static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+static END_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn session_running() -> bool {
+    SESSION.lock().unwrap().is_some()
+}
+
+pub fn session_info() -> Option<SessionInfo> {
+    let guard = SESSION.lock().unwrap();
+    let session = guard.as_ref()?;
+    Some(SessionInfo {
+        game_name: session.game.name().to_string(),
+        is_handler: matches!(session.game, Game::HandlerRef(_)),
+        players: session
+            .instances
+            .iter()
+            .map(|instance| instance.profname.trim_start_matches('.').to_string())
+            .collect(),
+        devices: session
+            .instances
+            .iter()
+            .flat_map(|instance| instance.devices.iter())
+            .map(|d| session.input_devices[*d].clone())
+            .collect(),
+    })
+}
+
+pub fn write_session_lock(session: &Session) -> Result<(), Box<dyn Error>> {
+    let game = match &session.game {
+        Game::HandlerRef(h) => LockGame::Handler(h.uid.clone()),
+        Game::ExecRef(e) => LockGame::Executable(e.path().clone(), e.args().to_string()),
+    };
+    let lock = SessionLock {
+        partydeck_pid: std::process::id(),
+        game_name: session.game.name().to_string(),
+        game,
+        instances: session.instances.clone(),
+        input_devices: session.input_devices.clone(),
+        gamepads: session.gamepads.clone(),
+        pgids: session.pgids.clone(),
+        starttimes: session
+            .pgids
+            .iter()
+            .map(|pgid| process_starttime(*pgid).unwrap_or_default())
+            .collect(),
+        boot_id: boot_id(),
+        kwin_script: session.kwin_script.clone(),
+        netns: session.netns,
+        virtual_pads: !session.vpads.is_empty(),
+    };
+    let file = File::create(PATH_PARTY.join("session.json"))?;
+    serde_json::to_writer_pretty(file, &lock)?;
+    Ok(())
+}
+
+pub fn read_session_lock() -> Option<SessionLock> {
+    let file = File::open(PATH_PARTY.join("session.json")).ok()?;
+    serde_json::from_reader(BufReader::new(file)).ok()
+}
+
+fn remove_session_lock() {
+    let path = PATH_PARTY.join("session.json");
+    if path.exists() {
+        if let Err(err) = std::fs::remove_file(path) {
+            println!("[PARTYDECK] Couldn't remove session lockfile: {err}");
+        }
+    }
+}
+
+// Takes over a session left behind by a PartyDeck that crashed or was killed
+pub fn reattach_session(lock: SessionLock) -> Result<(), Box<dyn Error>> {
+    if !lock.can_reattach() {
+        return Err("The session's virtual controllers closed along with PartyDeck".into());
+    }
+    let pgids = lock.verified_pgids();
+    let netns = lock.verified_netns();
+    let game = match lock.game {
+        LockGame::Handler(uid) => Game::HandlerRef(
+            scan_handlers()
+                .into_iter()
+                .find(|h| h.uid == uid)
+                .ok_or_else(|| format!("Handler {uid} not found"))?,
+        ),
+        LockGame::Executable(path, args) => Game::ExecRef(Executable::new(path, args)),
+    };
+
+    let session = Session {
+        game,
+        cfg: load_cfg(),
+        instances: lock.instances,
+        input_devices: lock.input_devices,
+        gamepads: lock.gamepads,
+        pgids,
+        children: Vec::new(),
+        kwin_script: lock.kwin_script,
+        netns,
+        vpads: Vec::new(),
+    };
+    write_session_lock(&session)?;
+    clear_end_request();
+    *SESSION.lock().unwrap() = Some(session);
+
+    println!("[PARTYDECK] Reattached to previous session");
+    std::thread::spawn(watch_session);
+    Ok(())
+}
+
+// Closes everything a crashed PartyDeck left running and removes its lockfile
+pub fn cleanup_stale_session(lock: SessionLock) -> Result<(), Box<dyn Error>> {
+    println!("[PARTYDECK] Cleaning up stale session...");
+    terminate_instances(&lock.verified_pgids(), &mut Vec::new());
+    if let Some(netns) = lock.verified_netns() {
+        netns.stop();
+    }
+    if let LockGame::Handler(uid) = &lock.game {
+        if let Some(h) = scan_handlers().into_iter().find(|h| h.uid == *uid) {
+            mirror_instance_dirs(&h, &lock.instances);
+        }
+    }
+    if lock.kwin_script.is_some() {
+        if let Err(err) = kwin_dbus_unload_script() {
+            println!("[PARTYDECK] Couldn't unload KWin script: {err}");
+        }
+    }
+    remove_session_lock();
+    remove_guest_profiles()?;
+    Ok(())
+}
+
+// Saves what each player's instance wrote into its game folder to their profile
+fn mirror_instance_dirs(h: &Handler, instances: &[Instance]) {
+    if !h.uses_instance_dirs() || !h.mirror_instance_dirs {
+        return;
+    }
+    for (i, instance) in instances.iter().enumerate() {
+        if instance.profname.starts_with('.') {
+            continue;
+        }
+        if let Err(err) = mirror_instance_dir(h, i, &instance.profname) {
+            println!(
+                "[PARTYDECK] Couldn't mirror {}'s game folder: {err}",
+                instance.profname
+            );
+        }
+    }
+}
+
+pub fn request_end_session() {
+    END_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn end_requested() -> bool {
+    END_REQUESTED.load(Ordering::SeqCst)
+}
+
+// Forgets requests made while no session was running, so they don't end the one starting
+pub fn clear_end_request() {
+    END_REQUESTED.store(false, Ordering::SeqCst);
+}
+
+extern "C" fn on_end_signal(_: libc::c_int) {
+    END_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+// SIGUSR1 ends the running session, e.g. from `partydeck --end-session`
+pub fn install_signal_handlers() {
+    unsafe {
+        libc::signal(
+            libc::SIGUSR1,
+            on_end_signal as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+// Asks the PartyDeck running a session to end it. Only the lockfile's owner is signalled, as
+// SIGUSR1 kills a PartyDeck that hasn't installed its handler yet.
+pub fn signal_end_session() -> Result<(), Box<dyn Error>> {
+    let pid = match read_session_lock() {
+        Some(lock) if lock.owner_alive() && lock.partydeck_pid != std::process::id() => {
+            lock.partydeck_pid
+        }
+        _ => return Err("No running PartyDeck session found".into()),
+    };
+    println!("[PARTYDECK] Sending end-session signal to {pid}");
+    signal_pid(pid, libc::SIGUSR1);
+    Ok(())
+}
+
+// Monitors the running session until every instance has exited or an end is requested,
+// then tears it down. Spawned on its own thread by launch_game.
+pub fn watch_session() {
+    let mut combo_since: Option<Instant> = None;
+    let mut combo_devices: HashMap<String, evdev::Device> = HashMap::new();
+    loop {
+        std::thread::sleep(Duration::from_millis(250));
+
+        let mut guard = SESSION.lock().unwrap();
+        let Some(session) = guard.as_mut() else {
+            return;
+        };
+
+        combo_devices.retain(|path, _| session.gamepads.contains(path));
+        if session
+            .gamepads
+            .iter()
+            .any(|path| end_combo_held(&mut combo_devices, path))
+        {
+            let since = combo_since.get_or_insert_with(Instant::now);
+            if since.elapsed() > COMBO_HOLD {
+                println!("[PARTYDECK] End-session combo held");
+                request_end_session();
+            }
+        } else {
+            combo_since = None;
+        }
+
+        for child in &mut session.children {
+            let _ = child.try_wait();
+        }
+        let all_exited = session.pgids.iter().all(|pgid| !pid_alive(*pgid));
+        drop(guard);
+
+        if all_exited || end_requested() {
+            break;
+        }
+    }
+
+    if let Err(err) = end_session() {
+        println!("[PARTYDECK] Error ending session: {err}");
+    }
+}
+
+// Gamepads stay open between checks, and are reopened if they stop answering, e.g. after
+// being unplugged
+fn end_combo_held(devices: &mut HashMap<String, evdev::Device>, path: &str) -> bool {
+    if !devices.contains_key(path) {
+        let Ok(dev) = evdev::Device::open(path) else {
+            return false;
+        };
+        devices.insert(path.to_string(), dev);
+    }
+    match devices[path].get_key_state() {
+        Ok(keys) => {
+            keys.contains(evdev::KeyCode::BTN_START) && keys.contains(evdev::KeyCode::BTN_SELECT)
+        }
+        Err(_) => {
+            devices.remove(path);
+            false
+        }
+    }
+}
+
+// Gracefully closes all instances, escalating to SIGTERM and then SIGKILL on each
+// instance's process tree, then unloads the KWin script and cleans up guest/temp data
+pub fn end_session() -> Result<(), Box<dyn Error>> {
+    let session = SESSION.lock().unwrap().take();
+
+    if let Some(mut session) = session {
+        println!("[PARTYDECK] Ending session...");
+
+        terminate_instances(&session.pgids, &mut session.children);
+        if let Some(netns) = &session.netns {
+            netns.stop();
+        }
+
+        if let Game::HandlerRef(h) = &session.game {
+            mirror_instance_dirs(h, &session.instances);
+        }
+
+        if session.kwin_script.is_some() {
+            if let Err(err) = kwin_dbus_unload_script() {
+                println!("[PARTYDECK] Couldn't unload KWin script: {err}");
+            }
+        }
+    }
+
+    END_REQUESTED.store(false, Ordering::SeqCst);
+
+    remove_session_lock();
+    remove_guest_profiles()?;
+    if PATH_PARTY.join("tmp").exists() {
+        std::fs::remove_dir_all(PATH_PARTY.join("tmp"))?;
+    }
+
+    println!("[PARTYDECK] Session ended.");
+    Ok(())
+}
+
+// Signals each instance's process tree with SIGINT, then SIGTERM, then SIGKILL,
+// waiting a grace period between each. Gamescope shuts down cleanly on SIGINT, taking the game with it.
+fn terminate_instances(pgids: &[u32], children: &mut Vec<Child>) {
+    for sig in [libc::SIGINT, libc::SIGTERM, libc::SIGKILL] {
+        let mut pids = Vec::new();
+        for pgid in pgids {
+            pids.extend(process_tree(*pgid));
+        }
+        pids.retain(|pid| pid_alive(*pid));
+        if pids.is_empty() {
+            break;
+        }
+        for pgid in pgids {
+            signal_group(*pgid, sig);
+        }
+        for pid in &pids {
+            signal_pid(*pid, sig);
+        }
+        let start = Instant::now();
+        while start.elapsed() < GRACE_PERIOD && pids.iter().any(|pid| pid_alive(*pid)) {
+            for child in children.iter_mut() {
+                let _ = child.try_wait();
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+    for child in children.iter_mut() {
+        let _ = child.try_wait();
+    }
+}
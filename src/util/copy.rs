@@ -0,0 +1,319 @@
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::SystemTime;
+
+// How much is copied between progress updates and cancellation checks
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+// Progress of the copy currently running, read by the GUI's loading overlay
+pub struct CopyProgress {
+    active: AtomicBool,
+    cancelled: AtomicBool,
+    bytes_done: AtomicU64,
+    bytes_total: AtomicU64,
+    files_done: AtomicU64,
+    files_total: AtomicU64,
+}
+
+pub static COPY_PROGRESS: CopyProgress = CopyProgress {
+    active: AtomicBool::new(false),
+    cancelled: AtomicBool::new(false),
+    bytes_done: AtomicU64::new(0),
+    bytes_total: AtomicU64::new(0),
+    files_done: AtomicU64::new(0),
+    files_total: AtomicU64::new(0),
+};
+
+impl CopyProgress {
+    pub fn active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    // Fraction done, and a "x / y MB, a / b files" description
+    pub fn status(&self) -> (f32, String) {
+        let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        let done = self.bytes_done.load(Ordering::Relaxed);
+        let total = self.bytes_total.load(Ordering::Relaxed);
+        let fraction = match total {
+            0 => 0.0,
+            total => done as f32 / total as f32,
+        };
+        let text = format!(
+            "{:.0} / {:.0} MB, {} / {} files",
+            mb(done),
+            mb(total),
+            self.files_done.load(Ordering::Relaxed),
+            self.files_total.load(Ordering::Relaxed)
+        );
+        (fraction, text)
+    }
+
+    fn start(&self, bytes: u64, files: u64) {
+        self.cancelled.store(false, Ordering::Relaxed);
+        self.bytes_done.store(0, Ordering::Relaxed);
+        self.files_done.store(0, Ordering::Relaxed);
+        self.bytes_total.store(bytes, Ordering::Relaxed);
+        self.files_total.store(files, Ordering::Relaxed);
+        self.active.store(true, Ordering::Relaxed);
+    }
+}
+
+// Folders copied from a source keep its mtime, which is set once everything inside is copied
+enum CopyOp {
+    Dir(PathBuf, Option<SystemTime>),
+    Symlink(PathBuf, PathBuf),
+    File(PathBuf, PathBuf, u64),
+}
+
+// Copies each (source, destination) pair, where the source is a file or a folder whose contents
+// are merged into the destination. Files are reflinked where the filesystem supports it, and
+// otherwise copied in parallel with copy_file_range. Free space is checked before anything is copied.
+pub fn copy_paths(jobs: &[(PathBuf, PathBuf)]) -> Result<(), Box<dyn Error>> {
+    let mut ops = Vec::new();
+    for (src, dest) in jobs {
+        collect_ops(src, dest, &mut ops)?;
+    }
+    check_free_space(&ops)?;
+
+    let files: Vec<(&PathBuf, &PathBuf, u64)> = ops
+        .iter()
+        .filter_map(|op| match op {
+            CopyOp::File(src, dest, size) => Some((src, dest, *size)),
+            _ => None,
+        })
+        .collect();
+    COPY_PROGRESS.start(
+        files.iter().map(|(_, _, size)| size).sum(),
+        files.len() as u64,
+    );
+
+    let result = copy_ops(&ops, &files);
+    COPY_PROGRESS.active.store(false, Ordering::Relaxed);
+    result
+}
+
+fn copy_ops(ops: &[CopyOp], files: &[(&PathBuf, &PathBuf, u64)]) -> Result<(), Box<dyn Error>> {
+    for op in ops {
+        match op {
+            CopyOp::Dir(dest, _) => std::fs::create_dir_all(dest)?,
+            CopyOp::Symlink(target, dest) => {
+                if dest.is_symlink() || dest.is_file() {
+                    std::fs::remove_file(dest)?;
+                }
+                std::os::unix::fs::symlink(target, dest)?;
+            }
+            CopyOp::File(..) => {}
+        }
+    }
+
+    // The first error stops the remaining copies; it's reported after the running ones finish
+    let error: Mutex<Option<String>> = Mutex::new(None);
+    files.par_iter().for_each(|(src, dest, _)| {
+        if COPY_PROGRESS.cancelled() || error.lock().unwrap().is_some() {
+            return;
+        }
+        if let Err(err) = copy_file(src, dest) {
+            error
+                .lock()
+                .unwrap()
+                .get_or_insert(format!("Couldn't copy {}: {err}", src.display()));
+        }
+    });
+
+    if COPY_PROGRESS.cancelled() {
+        return Err("Copy cancelled".into());
+    }
+    if let Some(err) = error.into_inner().unwrap() {
+        return Err(err.into());
+    }
+
+    // Deepest folders first, as setting a folder's mtime doesn't change its parent's
+    for op in ops.iter().rev() {
+        if let CopyOp::Dir(dest, Some(mtime)) = op {
+            File::open(dest)?.set_modified(*mtime)?;
+        }
+    }
+    Ok(())
+}
+
+fn collect_ops(src: &Path, dest: &Path, ops: &mut Vec<CopyOp>) -> Result<(), Box<dyn Error>> {
+    let meta = std::fs::symlink_metadata(src)?;
+    if !meta.is_dir() {
+        if let Some(parent) = dest.parent() {
+            ops.push(CopyOp::Dir(parent.to_path_buf(), None));
+        }
+        ops.push(op_for(src, dest.to_path_buf(), &meta)?);
+        return Ok(());
+    }
+
+    ops.push(CopyOp::Dir(dest.to_path_buf(), meta.modified().ok()));
+    for entry in walkdir::WalkDir::new(src).min_depth(1).follow_links(false) {
+        let entry = entry?;
+        let new_path = dest.join(entry.path().strip_prefix(src)?);
+        if entry.file_type().is_dir() {
+            ops.push(CopyOp::Dir(new_path, entry.metadata()?.modified().ok()));
+        } else {
+            ops.push(op_for(entry.path(), new_path, &entry.metadata()?)?);
+        }
+    }
+    Ok(())
+}
+
+fn op_for(src: &Path, dest: PathBuf, meta: &std::fs::Metadata) -> Result<CopyOp, Box<dyn Error>> {
+    match meta.is_symlink() {
+        true => Ok(CopyOp::Symlink(std::fs::read_link(src)?, dest)),
+        false => Ok(CopyOp::File(src.to_path_buf(), dest, meta.len())),
+    }
+}
+
+// Errors if any destination filesystem doesn't have room for what's copied to it.
+// Copies that can be reflinked don't use any space, so they aren't counted.
+fn check_free_space(ops: &[CopyOp]) -> Result<(), Box<dyn Error>> {
+    // Space needed per destination filesystem, along with a folder on it
+    let mut needed: HashMap<u64, (u64, PathBuf)> = HashMap::new();
+    let mut reflinks: HashMap<(u64, u64), bool> = HashMap::new();
+
+    for op in ops {
+        let CopyOp::File(src, dest, size) = op else {
+            continue;
+        };
+        let dest_dir = existing_ancestor(dest);
+        let dest_dev = std::fs::metadata(&dest_dir)?.dev();
+        let src_dev = std::fs::metadata(src)?.dev();
+        let reflink = *reflinks
+            .entry((src_dev, dest_dev))
+            .or_insert_with(|| can_reflink(src, &dest_dir));
+        if !reflink {
+            needed.entry(dest_dev).or_insert((0, dest_dir)).0 += size;
+        }
+    }
+
+    for (size, dir) in needed.values() {
+        let available = free_space(dir)?;
+        if *size > available {
+            return Err(format!(
+                "Not enough disk space in {}: {} MB needed, {} MB available",
+                dir.display(),
+                size / (1024 * 1024),
+                available / (1024 * 1024)
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn existing_ancestor(path: &Path) -> PathBuf {
+    path.ancestors()
+        .find(|p| p.is_dir())
+        .unwrap_or(Path::new("/"))
+        .to_path_buf()
+}
+
+fn free_space(dir: &Path) -> Result<u64, Box<dyn Error>> {
+    let path = std::ffi::CString::new(dir.as_os_str().as_encoded_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+// Tries reflinking a file into the folder to find out if the filesystems support it
+fn can_reflink(src: &Path, dest_dir: &Path) -> bool {
+    let probe = dest_dir.join(".partydeck_reflink_probe");
+    let result = File::open(src)
+        .and_then(|src| Ok((src, File::create(&probe)?)))
+        .is_ok_and(|(src, dest)| reflink(&src, &dest));
+    let _ = std::fs::remove_file(&probe);
+    result
+}
+
+fn reflink(src: &File, dest: &File) -> bool {
+    unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) == 0 }
+}
+
+fn copy_file(src: &Path, dest: &Path) -> Result<(), Box<dyn Error>> {
+    if dest.is_symlink() {
+        std::fs::remove_file(dest)?;
+    }
+    let mut src_file = File::open(src)?;
+    let mut dest_file = File::create(dest)?;
+    let size = src_file.metadata()?.len();
+
+    if reflink(&src_file, &dest_file) {
+        COPY_PROGRESS.bytes_done.fetch_add(size, Ordering::Relaxed);
+    } else {
+        copy_contents(&mut src_file, &mut dest_file)?;
+    }
+
+    let meta = src_file.metadata()?;
+    dest_file.set_modified(meta.modified()?)?;
+    dest_file.set_permissions(meta.permissions())?;
+    COPY_PROGRESS.files_done.fetch_add(1, Ordering::Relaxed);
+    Ok(())
+}
+
+// Uses copy_file_range, falling back to reading and writing when the kernel or
+// filesystems don't support it
+fn copy_contents(src: &mut File, dest: &mut File) -> Result<(), Box<dyn Error>> {
+    let mut use_range = true;
+    let mut buf = Vec::new();
+    loop {
+        if COPY_PROGRESS.cancelled() {
+            return Err("Copy cancelled".into());
+        }
+
+        let copied = if use_range {
+            let n = unsafe {
+                libc::copy_file_range(
+                    src.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    dest.as_raw_fd(),
+                    std::ptr::null_mut(),
+                    CHUNK_SIZE,
+                    0,
+                )
+            };
+            if n < 0 {
+                let err = std::io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::EXDEV | libc::ENOSYS | libc::EOPNOTSUPP | libc::EINVAL) => {
+                        use_range = false;
+                        continue;
+                    }
+                    _ => return Err(err.into()),
+                }
+            }
+            n as usize
+        } else {
+            buf.resize(CHUNK_SIZE, 0);
+            let n = src.read(&mut buf)?;
+            dest.write_all(&buf[..n])?;
+            n
+        };
+
+        if copied == 0 {
+            return Ok(());
+        }
+        COPY_PROGRESS
+            .bytes_done
+            .fetch_add(copied as u64, Ordering::Relaxed);
+    }
+}
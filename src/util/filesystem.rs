@@ -1,5 +1,6 @@
 use crate::handler::Handler;
 use crate::paths::*;
+use crate::util::copy::copy_paths;
 use rfd::FileDialog;
 use serde_json::Value;
 use std::error::Error;
@@ -19,6 +20,11 @@ pub fn copy_dir_recursive(
         dest.display()
     );
 
+    // Real copies go through the copy engine, which reports progress to the GUI
+    if !symlink_instead {
+        return copy_paths(&[(src.clone(), dest.clone())]);
+    }
+
     let walk_path = walkdir::WalkDir::new(src).min_depth(1).follow_links(false);
 
     for entry in walk_path {
@@ -145,6 +151,14 @@ fn find_saved_path(uid: &str) -> Option<Result<String, Box<dyn Error>>> {
     None
 }
 
+// Looks up a program in the directories listed in PATH
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = std::env::var("PATH").ok()?;
+    path.split(':')
+        .map(|dir| PathBuf::from(dir).join(program))
+        .find(|p| p.is_file())
+}
+
 pub trait SanitizePath {
     fn sanitize_path(&self) -> String;
 }
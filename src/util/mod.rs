@@ -1,4 +1,5 @@
 // Re-export all utility functions from submodules
+mod copy;
 mod filesystem;
 mod profiles;
 mod sys;
@@ -6,14 +7,23 @@ mod updates;
 
 // Re-export functions from profiles
 pub use profiles::{
-    GUEST_NAMES, create_gamesave, create_profile, remove_guest_profiles, scan_profiles,
+    GUEST_NAMES, create_gamesave, create_overlay_dirs, create_profile, remembered_profile,
+    remove_guest_profiles, save_device_assignments, scan_profiles,
 };
 
+// Re-export functions from copy
+pub use copy::{COPY_PROGRESS, copy_paths};
+
 // Re-export functions from filesystem
-pub use filesystem::{SanitizePath, copy_dir_recursive, get_rootpath, get_rootpath_handler};
+pub use filesystem::{
+    SanitizePath, copy_dir_recursive, find_in_path, get_rootpath, get_rootpath_handler,
+};
 
 // Re-export functions from launcher
-pub use sys::{get_screen_resolution, kwin_dbus_start_script, kwin_dbus_unload_script, msg, yesno};
+pub use sys::{
+    boot_id, get_screen_resolution, kwin_dbus_start_script, kwin_dbus_unload_script, msg,
+    pid_alive, process_starttime, process_tree, signal_group, signal_pid, yesno,
+};
 
 // Re-export functions from updates
 pub use updates::check_for_partydeck_update;
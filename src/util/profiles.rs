@@ -1,7 +1,11 @@
 use rand::prelude::*;
+use std::collections::HashMap;
 use std::error::Error;
 use std::path::PathBuf;
 
+use crate::input::{DeviceId, DeviceInfo};
+use crate::instance::Instance;
+use crate::session::session_info;
 use crate::util::filesystem::copy_dir_recursive;
 use crate::{handler::Handler, paths::*};
 
@@ -81,6 +85,18 @@ pub fn create_gamesave(name: &str, h: &Handler) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+// Creates the writable layer a profile's instance of an overlay mode game writes its changes to
+pub fn create_overlay_dirs(name: &str, h: &Handler) -> Result<(), Box<dyn Error>> {
+    let path_overlay = PATH_PARTY
+        .join("profiles")
+        .join(name)
+        .join("overlay")
+        .join(&h.uid);
+    std::fs::create_dir_all(path_overlay.join("upper"))?;
+    std::fs::create_dir_all(path_overlay.join("work"))?;
+    Ok(())
+}
+
 // Gets a vector of all available profiles.
 // include_guest true for building the profile selector dropdown, false for the profile viewer.
 pub fn scan_profiles(include_guest: bool) -> Vec<String> {
@@ -125,6 +141,50 @@ pub fn remove_guest_profiles() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+// Which profile last played with each device, keyed by DeviceId::key
+fn load_device_assignments() -> HashMap<String, String> {
+    std::fs::read_to_string(PATH_PARTY.join("device_assignments.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+// Remembers the profile each instance's devices were used with; guests aren't remembered
+pub fn save_device_assignments(
+    instances: &[Instance],
+    input_devices: &[DeviceInfo],
+) -> Result<(), Box<dyn Error>> {
+    let mut assignments = load_device_assignments();
+    for instance in instances {
+        if instance.profname.starts_with('.') {
+            continue;
+        }
+        for d in &instance.devices {
+            assignments.insert(input_devices[*d].id.key(), instance.profname.clone());
+        }
+    }
+    std::fs::write(
+        PATH_PARTY.join("device_assignments.json"),
+        serde_json::to_string_pretty(&assignments)?,
+    )?;
+    Ok(())
+}
+
+// Index into profiles of the profile that last used the device, or 0 (Guest) if it hasn't been
+// used or that profile is already taken by another instance or a player in the running session
+pub fn remembered_profile(id: &DeviceId, profiles: &[String], instances: &[Instance]) -> usize {
+    let Some(name) = load_device_assignments().remove(&id.key()) else {
+        return 0;
+    };
+    if session_info().is_some_and(|session| session.players.contains(&name)) {
+        return 0;
+    }
+    match profiles.iter().position(|profile| *profile == name) {
+        Some(i) if !instances.iter().any(|instance| instance.profselection == i) => i,
+        _ => 0,
+    }
+}
+
 pub static GUEST_NAMES: [&str; 31] = [
     "Blinky", "Pinky", "Inky", "Clyde", "Beatrice", "Battler", "Miyao", "Rena", "Ellie", "Joel",
     "Leon", "Ada", "Madeline", "Theo", "Yokatta", "Wyrm", "Brodiee", "Supreme", "Conk", "Gort",
@@ -71,3 +71,90 @@ pub fn kwin_dbus_unload_script() -> Result<(), Box<dyn Error>> {
     println!("Script unloaded.");
     Ok(())
 }
+
+// Reads (pid, ppid, pgrp, state) for every process in /proc
+fn proc_stats() -> Vec<(u32, u32, u32, char)> {
+    let mut out = Vec::new();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return out;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if let Some(stat) = read_proc_stat(pid) {
+            out.push(stat);
+        }
+    }
+    out
+}
+
+fn read_proc_stat(pid: u32) -> Option<(u32, u32, u32, char)> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The process name is in parentheses and may contain spaces, so parse after the last ')'
+    let rest = &stat[stat.rfind(')')? + 2..];
+    let mut fields = rest.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let ppid = fields.next()?.parse().ok()?;
+    let pgrp = fields.next()?.parse().ok()?;
+    Some((pid, ppid, pgrp, state))
+}
+
+// Returns every process in the given process group along with all of their descendants
+pub fn process_tree(pgid: u32) -> Vec<u32> {
+    let stats = proc_stats();
+    let mut out: Vec<u32> = stats
+        .iter()
+        .filter(|(pid, _, pgrp, _)| *pgrp == pgid || *pid == pgid)
+        .map(|(pid, _, _, _)| *pid)
+        .collect();
+    let mut i = 0;
+    while i < out.len() {
+        let parent = out[i];
+        for (pid, ppid, _, _) in &stats {
+            if *ppid == parent && !out.contains(pid) {
+                out.push(*pid);
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+pub fn pid_alive(pid: u32) -> bool {
+    match read_proc_stat(pid) {
+        Some((_, _, _, state)) => state != 'Z' && state != 'X',
+        None => false,
+    }
+}
+
+// When a process started, in clock ticks since boot (field 22 of /proc/<pid>/stat). Together
+// with boot_id this tells a process apart from a later one that reused its PID.
+pub fn process_starttime(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // Fields after the name start at the state, field 3
+    let rest = &stat[stat.rfind(')')? + 2..];
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+pub fn boot_id() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default()
+}
+
+pub fn signal_pid(pid: u32, sig: i32) {
+    unsafe {
+        libc::kill(pid as libc::pid_t, sig);
+    }
+}
+
+pub fn signal_group(pgid: u32, sig: i32) {
+    unsafe {
+        libc::killpg(pgid as libc::pid_t, sig);
+    }
+}
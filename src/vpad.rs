@@ -0,0 +1,384 @@
+use crate::app::PartyConfig;
+use crate::input::{DeviceId, DeviceInfo, DeviceType};
+use crate::instance::Instance;
+use crate::remap::{RemapProfile, Remapper};
+
+use evdev::uinput::VirtualDevice;
+use evdev::*;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::CString;
+use std::os::fd::AsRawFd;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// Physical path given to PartyDeck's virtual pads, so they can be told apart from real ones
+pub const VPAD_PHYS: &str = "partydeck-vpad";
+// How often a disconnected controller is looked for
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+// How long udev gets to give the user access to a new virtual pad's node
+const NODE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// A uinput gamepad standing in for a player's controller. The forwarding thread stops and the
+// pad is removed when this is dropped.
+pub struct VirtualPad {
+    pub node: String,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for VirtualPad {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Creates a virtual pad for each gamepad the instances use, and points the devices' virtual_path
+// at it. Without virtual pads turned on, only gamepads with a remap get one. A gamepad shared by
+// several instances gets a single pad, remapped for the first of them.
+pub fn create_virtual_pads(
+    input_devices: &mut [DeviceInfo],
+    instances: &[Instance],
+    cfg: &PartyConfig,
+) -> Result<Vec<VirtualPad>, Box<dyn Error>> {
+    let mut pads = Vec::new();
+    for instance in instances {
+        for d in &instance.devices {
+            let dev = &mut input_devices[*d];
+            if dev.device_type != DeviceType::Gamepad || dev.virtual_path.is_some() {
+                continue;
+            }
+            let remap = cfg.remap_for(&instance.profname, dev);
+            if !cfg.virtual_pads && remap.is_none() {
+                continue;
+            }
+            let pad = create_virtual_pad(&dev.path, remap)?;
+            dev.virtual_path = Some(pad.node.clone());
+            pads.push(pad);
+        }
+    }
+    Ok(pads)
+}
+
+// Clones the gamepad at path into a virtual pad, and starts forwarding its input to it through
+// the remap, if there is one
+pub fn create_virtual_pad(
+    path: &str,
+    remap: Option<&RemapProfile>,
+) -> Result<VirtualPad, Box<dyn Error>> {
+    let physical = Device::open(path)?;
+    physical.set_nonblocking(true)?;
+    let remapper = Remapper::new(remap.cloned().unwrap_or_default(), &physical);
+    let mut virt = build_virtual(&physical, &remapper)?;
+
+    let node = virt
+        .enumerate_dev_nodes_blocking()?
+        .flatten()
+        .find(|node| {
+            node.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("event"))
+        })
+        .ok_or_else(|| format!("Virtual pad for {path} has no event node"))?
+        .display()
+        .to_string();
+    let start = Instant::now();
+    while std::fs::File::open(&node).is_err() {
+        if start.elapsed() > NODE_TIMEOUT {
+            return Err(format!("Couldn't access virtual pad {node}").into());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let flags = unsafe { libc::fcntl(virt.as_raw_fd(), libc::F_GETFL) };
+    unsafe { libc::fcntl(virt.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK) };
+
+    match remap {
+        Some(remap) => println!(
+            "[PARTYDECK] Created virtual pad {node} for {path}, remapped with \"{}\"",
+            remap.name
+        ),
+        None => println!("[PARTYDECK] Created virtual pad {node} for {path}"),
+    }
+    let forwarder = Forwarder::new(physical, virt, remapper);
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop = stop.clone();
+        std::thread::spawn(move || forwarder.run(&stop))
+    };
+    Ok(VirtualPad {
+        node,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+// Gives the virtual pad the same name, IDs, buttons, axes and rumble as the physical one,
+// so games pick the same mappings for it, along with anything the remap sends that it lacks
+fn build_virtual(physical: &Device, remapper: &Remapper) -> std::io::Result<VirtualDevice> {
+    // uinput names are limited to 80 bytes
+    let name = physical.name().unwrap_or("Gamepad");
+    let end = (0..=name.len().min(70))
+        .rev()
+        .find(|i| name.is_char_boundary(*i))
+        .unwrap_or(0);
+    let name = &name[..end];
+    let phys = CString::new(VPAD_PHYS).unwrap();
+    let mut builder = VirtualDevice::builder()?
+        .name(name)
+        .input_id(physical.input_id())
+        .with_phys(&phys)?;
+    let mut keys = AttributeSet::<KeyCode>::new();
+    for key in physical
+        .supported_keys()
+        .into_iter()
+        .flat_map(|keys| keys.iter())
+    {
+        keys.insert(key);
+    }
+    for key in remapper.extra_buttons() {
+        keys.insert(key);
+    }
+    builder = builder.with_keys(&keys)?;
+    for (code, info) in physical.get_absinfo()? {
+        builder = builder.with_absolute_axis(&UinputAbsSetup::new(code, info))?;
+    }
+    for (code, (min, max)) in remapper.extra_axes() {
+        let info = AbsInfo::new(rest_value(code, min, max), min, max, 0, 0, 0);
+        builder = builder.with_absolute_axis(&UinputAbsSetup::new(code, info))?;
+    }
+    let ff = physical.supported_ff();
+    if let Some(ff) = ff.filter(|_| physical.max_ff_effects() > 0) {
+        builder = builder
+            .with_ff(ff)?
+            .with_ff_effects_max(physical.max_ff_effects() as u32);
+    }
+    builder.build()
+}
+
+struct Forwarder {
+    name: String,
+    identity: DeviceId,
+    virt: VirtualDevice,
+    physical: Option<Device>,
+    remapper: Remapper,
+    // Rumble effects the game uploaded, by their id on the virtual pad, along with the
+    // physical pad's copy. They're uploaded again when the controller reconnects.
+    effects: HashMap<i16, (FFEffectData, Option<FFEffect>)>,
+    // Buttons held on the virtual pad
+    held_keys: HashSet<KeyCode>,
+    // Where each axis rests, so a disconnected pad's sticks and triggers can be let go
+    rest: Vec<(AbsoluteAxisCode, i32)>,
+    frame: Vec<InputEvent>,
+    last_scan: Instant,
+}
+
+impl Forwarder {
+    fn new(physical: Device, virt: VirtualDevice, remapper: Remapper) -> Self {
+        let mut rest: Vec<(AbsoluteAxisCode, i32)> = physical
+            .get_absinfo()
+            .map(|axes| {
+                axes.map(|(code, info)| (code, rest_value(code, info.minimum(), info.maximum())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        for (code, (min, max)) in remapper.extra_axes() {
+            rest.push((code, rest_value(code, min, max)));
+        }
+        Forwarder {
+            name: physical.name().unwrap_or("Gamepad").to_string(),
+            identity: DeviceId::of(&physical),
+            virt,
+            physical: Some(physical),
+            remapper,
+            effects: HashMap::new(),
+            held_keys: HashSet::new(),
+            rest,
+            frame: Vec::new(),
+            last_scan: Instant::now(),
+        }
+    }
+
+    fn run(mut self, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            if self.physical.is_none() && self.last_scan.elapsed() > RECONNECT_INTERVAL {
+                self.reconnect();
+            }
+
+            let mut fds = vec![libc::pollfd {
+                fd: self.virt.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            }];
+            if let Some(physical) = &self.physical {
+                fds.push(libc::pollfd {
+                    fd: physical.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                });
+            }
+            unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, 250) };
+
+            if fds[0].revents != 0 {
+                self.handle_virtual();
+            }
+            if fds.len() > 1 && fds[1].revents != 0 {
+                self.forward_input();
+            }
+        }
+    }
+
+    // Passes the physical pad's input on, a frame at a time
+    fn forward_input(&mut self) {
+        let Some(physical) = &mut self.physical else {
+            return;
+        };
+        let fetched = physical.fetch_events().map(|events| events.collect());
+        let events: Vec<InputEvent> = match fetched {
+            Ok(events) => events,
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return,
+            Err(_) => {
+                self.disconnect();
+                return;
+            }
+        };
+
+        for event in events {
+            match event.destructure() {
+                EventSummary::Synchronization(_, SynchronizationCode::SYN_REPORT, _) => {
+                    self.emit_frame()
+                }
+                EventSummary::Synchronization(..) => {}
+                _ => self.frame.push(event),
+            }
+        }
+        self.emit_frame();
+    }
+
+    fn emit_frame(&mut self) {
+        if self.frame.is_empty() {
+            return;
+        }
+        let events = self.remapper.apply(&self.frame);
+        self.frame.clear();
+        for event in &events {
+            if let EventSummary::Key(_, code, value) = event.destructure() {
+                match value {
+                    0 => self.held_keys.remove(&code),
+                    _ => self.held_keys.insert(code),
+                };
+            }
+        }
+        let _ = self.virt.emit(&events);
+    }
+
+    // Lets go of everything the pad was holding, and forgets its copies of the rumble effects
+    fn disconnect(&mut self) {
+        println!(
+            "[PARTYDECK] {} disconnected, waiting for it to reconnect",
+            self.name
+        );
+        self.physical = None;
+        self.last_scan = Instant::now();
+        for (_, effect) in self.effects.values_mut() {
+            *effect = None;
+        }
+
+        self.frame.clear();
+        let mut events: Vec<InputEvent> = self
+            .held_keys
+            .drain()
+            .map(|code| *KeyEvent::new(code, 0))
+            .collect();
+        for (code, value) in &self.rest {
+            events.push(*AbsoluteAxisEvent::new(*code, *value));
+        }
+        let _ = self.virt.emit(&events);
+    }
+
+    fn reconnect(&mut self) {
+        self.last_scan = Instant::now();
+        for (path, mut dev) in evdev::enumerate() {
+            let is_gamepad = dev
+                .supported_keys()
+                .is_some_and(|keys| keys.contains(KeyCode::BTN_SOUTH));
+            if !is_gamepad
+                || !self.identity.matches(&DeviceId::of(&dev))
+                || dev.set_nonblocking(true).is_err()
+            {
+                continue;
+            }
+            println!(
+                "[PARTYDECK] {} reconnected as {}",
+                self.name,
+                path.display()
+            );
+            for (data, effect) in self.effects.values_mut() {
+                *effect = dev.upload_ff_effect(*data).ok();
+            }
+            self.physical = Some(dev);
+            return;
+        }
+    }
+
+    // Handles the game's rumble requests to the virtual pad
+    fn handle_virtual(&mut self) {
+        let events: Vec<InputEvent> = match self.virt.fetch_events() {
+            Ok(events) => events.collect(),
+            Err(_) => return,
+        };
+        for event in events {
+            match event.destructure() {
+                EventSummary::UInput(event, UInputCode::UI_FF_UPLOAD, _) => {
+                    self.upload_effect(event)
+                }
+                EventSummary::UInput(event, UInputCode::UI_FF_ERASE, _) => {
+                    if let Ok(erase) = self.virt.process_ff_erase(event) {
+                        self.effects.remove(&(erase.effect_id() as i16));
+                    }
+                }
+                EventSummary::ForceFeedback(_, FFEffectCode::FF_GAIN, value) => {
+                    if let Some(physical) = &mut self.physical {
+                        let _ = physical.set_ff_gain(value as u16);
+                    }
+                }
+                EventSummary::ForceFeedback(_, code, value) => {
+                    if let Some((_, Some(effect))) = self.effects.get_mut(&(code.0 as i16)) {
+                        let _ = match value {
+                            0 => effect.stop(),
+                            count => effect.play(count),
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn upload_effect(&mut self, event: UInputEvent) {
+        let Ok(upload) = self.virt.process_ff_upload(event) else {
+            return;
+        };
+        let (id, data) = (upload.effect_id(), upload.effect());
+        let effect = match (self.effects.remove(&id), &mut self.physical) {
+            (Some((_, Some(mut effect))), _) => effect.update(data).ok().map(|_| effect),
+            (_, Some(physical)) => physical.upload_ff_effect(data).ok(),
+            (_, None) => None,
+        };
+        self.effects.insert(id, (data, effect));
+    }
+}
+
+// Triggers rest at their minimum, sticks and hats in the middle
+fn rest_value(code: AbsoluteAxisCode, min: i32, max: i32) -> i32 {
+    match code {
+        AbsoluteAxisCode::ABS_Z
+        | AbsoluteAxisCode::ABS_RZ
+        | AbsoluteAxisCode::ABS_GAS
+        | AbsoluteAxisCode::ABS_BRAKE => min,
+        _ => (min + max) / 2,
+    }
+}